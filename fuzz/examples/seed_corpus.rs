@@ -0,0 +1,82 @@
+//! Seeds the fuzz corpus from capture files
+//!
+//! Every distinct packet goes to the `parse` corpus, its body without the
+//! type byte to the corpus of the matching `parse_*` target and the laps it
+//! contains to the `parse_lap` corpus.
+//!
+//! usage: `cargo run --example seed_corpus -- <capture>...`
+
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::Path,
+};
+
+use backmarker_fuzz::{capture, udp};
+
+/// seeds written per target and capture, captures repeat themselves a lot
+const MAX_PER_TARGET: usize = 64;
+
+/// body parser per inbound message type, in type byte order from 1
+const TARGETS: [&str; 7] = [
+    "parse_registration_result",
+    "parse_realtime_update",
+    "parse_realtime_car_update",
+    "parse_entry_list",
+    "parse_track_data",
+    "parse_entry_list_car",
+    "parse_broadcasting_event",
+];
+
+fn main() -> Result<(), String> {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+    let mut reader = udp::UdpReader::new();
+    reader.trace_fields(true);
+    let mut seen = HashSet::new();
+
+    for file in env::args().skip(1) {
+        let path = Path::new(&file);
+        let stem = path.file_stem().map_or(String::from("capture"), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+        let mut written = HashMap::<String, usize>::new();
+        let mut add = |target: String, bytes: &[u8]| -> Result<(), String> {
+            let count = written.entry(target.clone()).or_default();
+            if *count >= MAX_PER_TARGET || !seen.insert((target.clone(), bytes.to_vec())) {
+                return Ok(());
+            }
+            let dir = corpus.join(&target);
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(dir.join(format!("{}-{:03}", stem, count)), bytes)
+                .map_err(|e| e.to_string())?;
+            *count += 1;
+            Ok(())
+        };
+
+        let packets = capture::read(path)?;
+        for packet in &packets {
+            add(String::from("parse"), &packet.bytes)?;
+            let target = packet
+                .bytes
+                .first()
+                .and_then(|kind| TARGETS.get((*kind as usize).wrapping_sub(1)));
+            if let Some(target) = target {
+                // packets that fail to decode are good seeds too
+                add(target.to_string(), &packet.bytes[1..])?;
+            }
+            reader.load(&packet.bytes)?;
+            let _ = udp::parse(&mut reader);
+            for field in reader.fields() {
+                if field.name.ends_with(".laptime_ms") {
+                    add(String::from("parse_lap"), &packet.bytes[field.offset..])?;
+                }
+            }
+        }
+        println!(
+            "seeded from {} packets of {}",
+            packets.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use backmarker_fuzz::{run, udp};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| run(data, udp::parse_entry_list));
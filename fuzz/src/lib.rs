@@ -0,0 +1,36 @@
+//! Shared setup for the parser fuzz targets
+//!
+//! The main crate is a binary, so the UDP and capture modules are compiled
+//! in here directly. Every target feeds the fuzzer input to one parser
+//! through a reader with field tracing on, any panic is a bug.
+
+use std::cell::RefCell;
+
+#[allow(dead_code, clippy::new_without_default)]
+#[path = "../../src/udp.rs"]
+pub mod udp;
+
+#[allow(dead_code)]
+#[path = "../../src/capture.rs"]
+pub mod capture;
+
+/// the packets the udp tests feed through the parsers
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../../src/fixture.rs"]
+mod fixture;
+
+thread_local! {
+    /// binding a socket per input would dominate the run time
+    static READER: RefCell<udp::UdpReader> = RefCell::new(udp::UdpReader::new());
+}
+
+/// loads `data` as a packet and runs `parse` over it, errors are expected
+pub fn run<T>(data: &[u8], parse: fn(&mut udp::UdpReader) -> Result<T, String>) {
+    READER.with_borrow_mut(|reader| {
+        reader.trace_fields(true);
+        if reader.load(data).is_ok() {
+            let _ = parse(reader);
+        }
+    });
+}
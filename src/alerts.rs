@@ -0,0 +1,35 @@
+//! Module for strategy alerts
+//!
+//! Alerts are raised by the strategy models and shown on the alert board.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    /// session time (ms) the alert was raised at
+    pub session_time: f32,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Alert {
+    pub fn info(session_time: f32, text: String) -> Self {
+        Alert {
+            session_time,
+            severity: Severity::Info,
+            text,
+        }
+    }
+
+    pub fn warning(session_time: f32, text: String) -> Self {
+        Alert {
+            session_time,
+            severity: Severity::Warning,
+            text,
+        }
+    }
+}
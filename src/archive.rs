@@ -0,0 +1,953 @@
+//! Module for the session archive
+//!
+//! Keeps every session in an SQLite database in the data directory so past
+//! races can be reviewed and compared. The schema version lives in the
+//! database's `user_version`, `MIGRATIONS` brings older files up to date.
+//! Live sessions are saved through `Writer`, which does the database work
+//! on a thread of its own.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, error, info};
+use rusqlite::{
+    params, params_from_iter,
+    types::{ToSqlOutput, Value},
+    Connection, OptionalExtension, ToSql, Transaction,
+};
+
+use crate::{
+    positions::{ChangeKind, PositionChange},
+    race_control,
+    session::{Car, Lap, PitStop, Session},
+    udp, utils, weather,
+};
+
+/// one entry per schema version, each upgrades from the version before it
+const MIGRATIONS: &[&str] = &[
+    // version 1
+    "CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        archived_at INTEGER NOT NULL,
+        event_index INTEGER NOT NULL,
+        session_index INTEGER NOT NULL,
+        session_type TEXT NOT NULL,
+        track_id INTEGER,
+        track_name TEXT,
+        track_meters INTEGER,
+        session_time REAL NOT NULL,
+        session_end_time REAL NOT NULL,
+        mandatory_stops INTEGER NOT NULL,
+        focused_car_index INTEGER
+    );
+    CREATE TABLE cars (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        car_index INTEGER NOT NULL,
+        race_number INTEGER NOT NULL,
+        car_model_type INTEGER NOT NULL,
+        team_name TEXT NOT NULL,
+        cup_category INTEGER NOT NULL,
+        nationality INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (session_id, car_index)
+    );
+    CREATE TABLE drivers (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        car_index INTEGER NOT NULL,
+        driver_index INTEGER NOT NULL,
+        first_name TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        short_name TEXT NOT NULL,
+        category INTEGER NOT NULL,
+        nationality INTEGER NOT NULL,
+        PRIMARY KEY (session_id, car_index, driver_index)
+    );
+    CREATE TABLE laps (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        car_index INTEGER NOT NULL,
+        number INTEGER NOT NULL,
+        driver_index INTEGER NOT NULL,
+        laptime_ms INTEGER NOT NULL,
+        sector1_ms INTEGER,
+        sector2_ms INTEGER,
+        sector3_ms INTEGER,
+        is_invalid INTEGER NOT NULL,
+        lap_type TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        cup_position INTEGER NOT NULL,
+        completed_at REAL NOT NULL,
+        neutralized INTEGER NOT NULL,
+        PRIMARY KEY (session_id, car_index, number)
+    );
+    CREATE TABLE pit_stops (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        car_index INTEGER NOT NULL,
+        lap INTEGER NOT NULL,
+        entry_time REAL NOT NULL,
+        exit_time REAL,
+        entry_spline REAL NOT NULL,
+        exit_spline REAL,
+        PRIMARY KEY (session_id, car_index, entry_time)
+    );
+    CREATE TABLE stints (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        car_index INTEGER NOT NULL,
+        number INTEGER NOT NULL,
+        first_lap INTEGER NOT NULL,
+        last_lap INTEGER NOT NULL,
+        PRIMARY KEY (session_id, car_index, number)
+    );
+    CREATE TABLE events (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        session_time REAL NOT NULL,
+        event_type TEXT NOT NULL,
+        car_index INTEGER,
+        text TEXT NOT NULL
+    );
+    CREATE TABLE position_changes (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        session_time REAL NOT NULL,
+        lap INTEGER NOT NULL,
+        car_index INTEGER NOT NULL,
+        from_position INTEGER NOT NULL,
+        to_position INTEGER NOT NULL,
+        class_from INTEGER NOT NULL,
+        class_to INTEGER NOT NULL,
+        passed TEXT NOT NULL,
+        kind TEXT NOT NULL
+    );
+    CREATE TABLE weather (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        session_time REAL NOT NULL,
+        ambient_temp INTEGER NOT NULL,
+        track_temp INTEGER NOT NULL,
+        clouds REAL NOT NULL,
+        rain_level REAL NOT NULL,
+        wetness REAL NOT NULL,
+        PRIMARY KEY (session_id, session_time)
+    );",
+    // version 2
+    "ALTER TABLE pit_stops ADD COLUMN stationary_ms REAL;",
+];
+
+/// current schema version
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+fn sql_error(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+/// One row of the session list
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub id: i64,
+    /// local date and time the session was first archived
+    pub date: String,
+    pub track_name: Option<String>,
+    pub session_type: String,
+    /// session time reached in ms
+    pub session_time: f32,
+    pub cars: usize,
+}
+
+pub struct Archive {
+    connection: Connection,
+}
+
+impl Archive {
+    /// opens the archive in the data directory
+    pub fn open() -> Result<Self, String> {
+        std::fs::create_dir_all(utils::data_dir()).map_err(|e| e.to_string())?;
+        Self::open_path(&utils::data_dir().join("archive.sqlite"))
+    }
+
+    pub fn open_path(path: &Path) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(sql_error)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(sql_error)?;
+        // the GUI reads while the writer thread saves
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(sql_error)?;
+        let mut archive = Archive { connection };
+        archive.migrate()?;
+        Ok(archive)
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: u32 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sql_error)?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "archive schema version {} is newer than supported {}",
+                version, SCHEMA_VERSION
+            ));
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let transaction = self.connection.transaction().map_err(sql_error)?;
+            transaction.execute_batch(migration).map_err(sql_error)?;
+            transaction
+                .pragma_update(None, "user_version", i as u32 + 1)
+                .map_err(sql_error)?;
+            transaction.commit().map_err(sql_error)?;
+            info!("archive migrated to schema version {}", i + 1);
+        }
+        Ok(())
+    }
+
+    /// writes the whole session, `id` is the row from an earlier save of
+    /// the same session, the row id is returned
+    pub fn save(&mut self, id: Option<i64>, session: &Session) -> Result<i64, String> {
+        self.write(id, &Snapshot::take(session)?)
+    }
+
+    /// writes a snapshot taken with `Snapshot::take`, like `save`
+    pub fn write(&mut self, id: Option<i64>, snapshot: &Snapshot) -> Result<i64, String> {
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        let id = match id {
+            Some(id) => {
+                transaction
+                    .execute(
+                        "UPDATE sessions SET event_index = ?1, session_index = ?2,
+                            session_type = ?3, track_id = ?4, track_name = ?5,
+                            track_meters = ?6, session_time = ?7, session_end_time = ?8,
+                            mandatory_stops = ?9, focused_car_index = ?10
+                         WHERE id = ?11",
+                        params_from_iter(snapshot.header.iter().chain([&Value::Integer(id)])),
+                    )
+                    .map_err(sql_error)?;
+                id
+            }
+            None => {
+                let archived_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs() as i64);
+                transaction
+                    .execute(
+                        "INSERT INTO sessions (event_index, session_index, session_type,
+                            track_id, track_name, track_meters, session_time,
+                            session_end_time, mandatory_stops, focused_car_index, archived_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params_from_iter(
+                            snapshot.header.iter().chain([&Value::Integer(archived_at)]),
+                        ),
+                    )
+                    .map_err(sql_error)?;
+                transaction.last_insert_rowid()
+            }
+        };
+        write_details(&transaction, id, &snapshot.rows).map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        debug!("archived session {}", id);
+        Ok(id)
+    }
+
+    /// a second connection to the same file
+    pub fn reopen(&self) -> Result<Archive, String> {
+        match self.connection.path() {
+            Some(path) if !path.is_empty() => Archive::open_path(Path::new(path)),
+            _ => Err(String::from("an in memory archive cannot be reopened")),
+        }
+    }
+}
+
+impl Archive {
+    /// every archived session, newest first
+    pub fn sessions(&self) -> Result<Vec<Summary>, String> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT id, datetime(archived_at, 'unixepoch', 'localtime'), track_name,
+                    session_type, session_time,
+                    (SELECT COUNT(*) FROM cars WHERE cars.session_id = sessions.id)
+                 FROM sessions ORDER BY archived_at DESC, id DESC",
+            )
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok(Summary {
+                    id: row.get(0)?,
+                    date: row.get(1)?,
+                    track_name: row.get(2)?,
+                    session_type: row.get(3)?,
+                    session_time: row.get(4)?,
+                    cars: row.get(5)?,
+                })
+            })
+            .map_err(sql_error)?;
+        rows.collect::<Result<_, _>>().map_err(sql_error)
+    }
+
+    /// reads a session back into a model the views can render
+    pub fn load(&self, id: i64) -> Result<Session, String> {
+        load_session(&self.connection, id)
+            .map_err(sql_error)?
+            .ok_or_else(|| format!("no archived session {}", id))
+    }
+}
+
+/// finds the variant of a stored `{:?}` name
+fn from_name<T: std::fmt::Debug + Copy>(name: &str, values: &[T]) -> Option<T> {
+    values
+        .iter()
+        .copied()
+        .find(|value| format!("{:?}", value) == name)
+}
+
+fn load_session(connection: &Connection, id: i64) -> rusqlite::Result<Option<Session>> {
+    use udp::{BroadcastingEventType, LapType, RaceSessionType};
+
+    let Some(mut realtime) = connection
+        .query_row(
+            "SELECT event_index, session_index, session_type, session_time, session_end_time,
+                focused_car_index
+             FROM sessions WHERE id = ?1",
+            [id],
+            |row| {
+                let session_type: String = row.get(2)?;
+                let focused: Option<u16> = row.get(5)?;
+                Ok(udp::RealtimeUpdate {
+                    event_index: row.get(0)?,
+                    session_index: row.get(1)?,
+                    session_type: from_name(
+                        &session_type,
+                        &(0..=u8::MAX)
+                            .filter_map(|value| RaceSessionType::try_from(value).ok())
+                            .collect::<Vec<_>>(),
+                    )
+                    .unwrap_or(RaceSessionType::Practice),
+                    phase: udp::SessionPhase::SessionOver,
+                    session_time: row.get(3)?,
+                    session_end_time: row.get(4)?,
+                    focused_car_index: focused.map_or(u32::MAX, u32::from),
+                    active_camera_set: String::new(),
+                    active_camera: String::new(),
+                    current_hud_page: String::new(),
+                    is_replay_playing: false,
+                    replay_session_time: None,
+                    replay_remaining_time: None,
+                    time_of_day: 0.0,
+                    ambiant_temp: 0,
+                    track_temp: 0,
+                    clouds: 0.0,
+                    rain_level: 0.0,
+                    wetness: 0.0,
+                    best_session_lap: udp::LapInfo::empty(0),
+                })
+            },
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let mut session = Session::new();
+    let (track, mandatory_stops): (Option<(String, u32, u32)>, u16) = connection.query_row(
+        "SELECT track_name, track_id, track_meters, mandatory_stops FROM sessions WHERE id = ?1",
+        [id],
+        |row| {
+            let track = match (row.get(0)?, row.get(1)?, row.get(2)?) {
+                (Some(name), Some(track_id), Some(meters)) => Some((name, track_id, meters)),
+                _ => None,
+            };
+            Ok((track, row.get(3)?))
+        },
+    )?;
+    if let Some((name, track_id, meters)) = track {
+        session.apply_track_data(udp::TrackData::stored(name, track_id, meters));
+    }
+    session.mandatory_stops = mandatory_stops;
+
+    let mut drivers: HashMap<u16, Vec<udp::DriverInfo>> = HashMap::new();
+    let mut statement = connection.prepare(
+        "SELECT car_index, first_name, last_name, short_name, category, nationality
+         FROM drivers WHERE session_id = ?1 ORDER BY car_index, driver_index",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        drivers
+            .entry(row.get(0)?)
+            .or_default()
+            .push(udp::DriverInfo {
+                first_name: row.get(1)?,
+                last_name: row.get(2)?,
+                short_name: row.get(3)?,
+                category: row.get(4)?,
+                nationality: row.get(5)?,
+            });
+    }
+
+    let mut positions: HashMap<u16, u16> = HashMap::new();
+    let mut statement = connection.prepare(
+        "SELECT car_index, race_number, car_model_type, team_name, cup_category, nationality,
+            position
+         FROM cars WHERE session_id = ?1",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        let car_index: u16 = row.get(0)?;
+        positions.insert(car_index, row.get(6)?);
+        session.cars.insert(
+            car_index,
+            Car::new(udp::CarInfo {
+                car_index,
+                car_model_type: row.get(2)?,
+                team_name: row.get(3)?,
+                race_number: row.get(1)?,
+                cup_category: row.get(4)?,
+                current_driver_index: 0,
+                drivers: drivers.remove(&car_index).unwrap_or_default(),
+                nationality: row.get(5)?,
+            }),
+        );
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT car_index, number, driver_index, laptime_ms, sector1_ms, sector2_ms,
+            sector3_ms, is_invalid, lap_type, position, cup_position, completed_at, neutralized
+         FROM laps WHERE session_id = ?1 ORDER BY car_index, number",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        let car_index: u16 = row.get(0)?;
+        let Some(car) = session.cars.get_mut(&car_index) else {
+            continue;
+        };
+        let lap_type: String = row.get(8)?;
+        let is_invalid: bool = row.get(7)?;
+        let splits: [Option<u32>; 3] = [row.get(4)?, row.get(5)?, row.get(6)?];
+        car.laps.push(Lap {
+            number: row.get(1)?,
+            info: udp::LapInfo {
+                laptime_ms: row.get(3)?,
+                car_index,
+                driver_index: row.get(2)?,
+                lap_splits: splits.iter().map(|split| split.unwrap_or(0)).collect(),
+                is_invalid,
+                is_valid_for_best: !is_invalid,
+                lap_type: from_name(
+                    &lap_type,
+                    &[LapType::Outlap, LapType::Inlap, LapType::Regular],
+                )
+                .unwrap_or(LapType::Regular),
+            },
+            completed_at: row.get(11)?,
+            position: row.get(9)?,
+            cup_position: row.get(10)?,
+            neutralized: row.get(12)?,
+        });
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT car_index, lap, entry_time, exit_time, entry_spline, exit_spline, stationary_ms
+         FROM pit_stops WHERE session_id = ?1 ORDER BY car_index, entry_time",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        if let Some(car) = session.cars.get_mut(&row.get(0)?) {
+            car.pit_stops.push(PitStop {
+                lap: row.get(1)?,
+                entry_time: row.get(2)?,
+                exit_time: row.get(3)?,
+                entry_spline: row.get(4)?,
+                exit_spline: row.get(5)?,
+                stationary_ms: row.get(6)?,
+            });
+        }
+    }
+
+    // the final state of each car stands in for the last live update
+    let mut best_session_lap: Option<udp::LapInfo> = None;
+    for car in session.cars.values_mut() {
+        let index = car.car_info.car_index;
+        let valid = |lap: &&Lap| !lap.info.is_invalid && lap.info.laptime_ms > 0;
+        let best = car
+            .laps
+            .iter()
+            .filter(valid)
+            .min_by_key(|lap| lap.info.laptime_ms)
+            .map_or(udp::LapInfo::empty(index), |lap| lap.info.clone());
+        if best.laptime_ms
+            < best_session_lap
+                .as_ref()
+                .map_or(u32::MAX, |lap| lap.laptime_ms)
+        {
+            best_session_lap = Some(best.clone());
+        }
+        let last = car.laps.last();
+        car.car_info.current_driver_index = last.map_or(0, |lap| lap.info.driver_index as u8);
+        car.realtime = Some(udp::RealtimeCarUpdate {
+            car_index: index,
+            driver_index: car.car_info.current_driver_index as u16,
+            driver_count: car.car_info.drivers.len() as u8,
+            gear: 1,
+            world_pos_x: 0.0,
+            world_pos_y: 0.0,
+            yaw: 0.0,
+            car_location: udp::CarLocation::None,
+            kmh: 0,
+            position: positions.get(&index).copied().unwrap_or(0),
+            cup_position: last.map_or(0, |lap| lap.cup_position),
+            track_position: 0,
+            spline_position: 0.0,
+            laps: last.map_or(0, |lap| lap.number),
+            delta: 0,
+            best_session_lap: best,
+            last_lap: last.map_or(udp::LapInfo::empty(index), |lap| lap.info.clone()),
+            current_lap: udp::LapInfo::empty(index),
+        });
+    }
+    if let Some(best) = best_session_lap {
+        realtime.best_session_lap = best;
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT session_time, ambient_temp, track_temp, clouds, rain_level, wetness
+         FROM weather WHERE session_id = ?1 ORDER BY session_time",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        session.weather.samples.push(weather::WeatherSample {
+            session_time: row.get(0)?,
+            clouds: row.get(3)?,
+            rain_level: row.get(4)?,
+            wetness: row.get(5)?,
+            ambient_temp: row.get(1)?,
+            track_temp: row.get(2)?,
+        });
+    }
+    if let Some(sample) = session.weather.latest() {
+        realtime.ambiant_temp = sample.ambient_temp;
+        realtime.track_temp = sample.track_temp;
+        realtime.clouds = sample.clouds;
+        realtime.rain_level = sample.rain_level;
+        realtime.wetness = sample.wetness;
+    }
+
+    let event_types: Vec<BroadcastingEventType> = (0..=u8::MAX)
+        .filter_map(|value| BroadcastingEventType::try_from(value).ok())
+        .collect();
+    let mut statement = connection.prepare(
+        "SELECT session_time, event_type, car_index, text
+         FROM events WHERE session_id = ?1 ORDER BY rowid",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        let event_type: String = row.get(1)?;
+        let entry = race_control::Entry {
+            session_time: row.get(0)?,
+            event_type: from_name(&event_type, &event_types).unwrap_or(BroadcastingEventType::None),
+            car_index: row.get(2)?,
+            text: row.get(3)?,
+        };
+        if entry.event_type == BroadcastingEventType::PenaltyCommMsg {
+            if let Some(car) = entry
+                .car_index
+                .and_then(|index| session.cars.get_mut(&index))
+            {
+                car.penalties.push(race_control::Penalty::parse(
+                    entry.session_time,
+                    &entry.text,
+                ));
+            }
+        }
+        session.race_control.push(entry);
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT session_time, lap, car_index, from_position, to_position, class_from,
+            class_to, passed, kind
+         FROM position_changes WHERE session_id = ?1 ORDER BY rowid",
+    )?;
+    let mut rows = statement.query([id])?;
+    while let Some(row) = rows.next()? {
+        let car_index: u16 = row.get(2)?;
+        let Some(car) = session.cars.get(&car_index) else {
+            continue;
+        };
+        let passed: String = row.get(7)?;
+        let kind: String = row.get(8)?;
+        let change = PositionChange {
+            session_time: row.get(0)?,
+            lap: row.get(1)?,
+            car_index,
+            race_number: car.car_info.race_number,
+            class: car.class(),
+            from: row.get(3)?,
+            to: row.get(4)?,
+            class_from: row.get(5)?,
+            class_to: row.get(6)?,
+            passed: passed
+                .split(',')
+                .filter_map(|index| index.parse::<u16>().ok())
+                .filter_map(|index| {
+                    let car = session.cars.get(&index)?;
+                    Some((index, car.car_info.race_number))
+                })
+                .collect(),
+            kind: from_name(&kind, &ChangeKind::ALL).unwrap_or(ChangeKind::OnTrack),
+        };
+        session.position_log.push(change);
+    }
+
+    session.realtime = Some(realtime);
+    Ok(Some(session))
+}
+
+const CAR_SQL: &str = "INSERT INTO cars (session_id, car_index, race_number, car_model_type,
+        team_name, cup_category, nationality, position)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+const DRIVER_SQL: &str = "INSERT INTO drivers (session_id, car_index, driver_index, first_name,
+        last_name, short_name, category, nationality)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+const LAP_SQL: &str = "INSERT INTO laps (session_id, car_index, number, driver_index, laptime_ms,
+        sector1_ms, sector2_ms, sector3_ms, is_invalid, lap_type, position,
+        cup_position, completed_at, neutralized)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)";
+const STOP_SQL: &str = "INSERT OR REPLACE INTO pit_stops (session_id, car_index, lap, entry_time,
+        exit_time, entry_spline, exit_spline, stationary_ms)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+const STINT_SQL: &str = "INSERT INTO stints (session_id, car_index, number, first_lap, last_lap)
+     VALUES (?1, ?2, ?3, ?4, ?5)";
+const EVENT_SQL: &str = "INSERT INTO events (session_id, session_time, event_type, car_index, text)
+     VALUES (?1, ?2, ?3, ?4, ?5)";
+const CHANGE_SQL: &str = "INSERT INTO position_changes (session_id, session_time, lap, car_index,
+        from_position, to_position, class_from, class_to, passed, kind)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)";
+const WEATHER_SQL: &str = "INSERT INTO weather (session_id, session_time, ambient_temp,
+        track_temp, clouds, rain_level, wetness)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+
+/// One row below the session row, `sql` binds the session id as `?1` and
+/// `values` from `?2` on
+struct Row {
+    sql: &'static str,
+    values: Vec<Value>,
+}
+
+/// copies borrowed parameters into values that outlive the session
+fn owned(params: &[&dyn ToSql]) -> rusqlite::Result<Vec<Value>> {
+    params
+        .iter()
+        .map(|param| match param.to_sql()? {
+            ToSqlOutput::Owned(value) => Ok(value),
+            ToSqlOutput::Borrowed(value) => Ok(value.into()),
+            _ => Err(rusqlite::Error::ToSqlConversionFailure(
+                "unsupported parameter".into(),
+            )),
+        })
+        .collect()
+}
+
+/// Everything a save writes, taken from the session so the database work
+/// can run on another thread
+pub struct Snapshot {
+    /// the session row from `event_index` to `focused_car_index`
+    header: Vec<Value>,
+    rows: Vec<Row>,
+}
+
+impl Snapshot {
+    pub fn take(session: &Session) -> Result<Self, String> {
+        let Some(realtime) = &session.realtime else {
+            return Err(String::from("no session to archive yet"));
+        };
+        let track = session.track.as_ref();
+        let header = owned(params![
+            realtime.event_index,
+            realtime.session_index,
+            format!("{:?}", realtime.session_type),
+            track.map(|track| track.track_id),
+            track.map(|track| track.track_name.clone()),
+            track.map(|track| track.track_meters),
+            realtime.session_time,
+            realtime.session_end_time,
+            session.mandatory_stops,
+            session.focused_car(),
+        ])
+        .map_err(sql_error)?;
+        Ok(Snapshot {
+            header,
+            rows: rows(session).map_err(sql_error)?,
+        })
+    }
+}
+
+fn rows(session: &Session) -> rusqlite::Result<Vec<Row>> {
+    let mut rows = vec![];
+    let mut push = |sql: &'static str, params: &[&dyn ToSql]| -> rusqlite::Result<()> {
+        rows.push(Row {
+            sql,
+            values: owned(params)?,
+        });
+        Ok(())
+    };
+    for car in session.cars.values() {
+        let info = &car.car_info;
+        push(
+            CAR_SQL,
+            params![
+                info.car_index,
+                info.race_number,
+                info.car_model_type,
+                info.team_name,
+                info.cup_category,
+                info.nationality,
+                car.position(),
+            ],
+        )?;
+        for (driver_index, driver) in info.drivers.iter().enumerate() {
+            push(
+                DRIVER_SQL,
+                params![
+                    info.car_index,
+                    driver_index,
+                    driver.first_name,
+                    driver.last_name,
+                    driver.short_name,
+                    driver.category,
+                    driver.nationality,
+                ],
+            )?;
+        }
+        for lap in &car.laps {
+            let split = |sector: usize| lap.info.lap_splits.get(sector).copied();
+            push(
+                LAP_SQL,
+                params![
+                    info.car_index,
+                    lap.number,
+                    lap.info.driver_index,
+                    lap.info.laptime_ms,
+                    split(0),
+                    split(1),
+                    split(2),
+                    lap.info.is_invalid,
+                    format!("{:?}", lap.info.lap_type),
+                    lap.position,
+                    lap.cup_position,
+                    lap.completed_at,
+                    lap.neutralized,
+                ],
+            )?;
+        }
+        for stop in &car.pit_stops {
+            push(
+                STOP_SQL,
+                params![
+                    info.car_index,
+                    stop.lap,
+                    stop.entry_time,
+                    stop.exit_time,
+                    stop.entry_spline,
+                    stop.exit_spline,
+                    stop.stationary_ms,
+                ],
+            )?;
+        }
+        for (number, (first_lap, last_lap)) in car.stints().into_iter().enumerate() {
+            push(
+                STINT_SQL,
+                params![info.car_index, number + 1, first_lap, last_lap],
+            )?;
+        }
+    }
+
+    for entry in &session.race_control {
+        push(
+            EVENT_SQL,
+            params![
+                entry.session_time,
+                format!("{:?}", entry.event_type),
+                entry.car_index,
+                entry.text,
+            ],
+        )?;
+    }
+
+    for change in &session.position_log {
+        let passed: Vec<String> = change
+            .passed
+            .iter()
+            .map(|(index, _)| index.to_string())
+            .collect();
+        push(
+            CHANGE_SQL,
+            params![
+                change.session_time,
+                change.lap,
+                change.car_index,
+                change.from,
+                change.to,
+                change.class_from,
+                change.class_to,
+                passed.join(","),
+                format!("{:?}", change.kind),
+            ],
+        )?;
+    }
+
+    for sample in &session.weather.samples {
+        push(
+            WEATHER_SQL,
+            params![
+                sample.session_time,
+                sample.ambient_temp,
+                sample.track_temp,
+                sample.clouds,
+                sample.rain_level,
+                sample.wetness,
+            ],
+        )?;
+    }
+    Ok(rows)
+}
+
+/// replaces everything stored below the session row
+fn write_details(transaction: &Transaction, id: i64, rows: &[Row]) -> rusqlite::Result<()> {
+    for table in [
+        "cars",
+        "drivers",
+        "laps",
+        "pit_stops",
+        "stints",
+        "events",
+        "position_changes",
+        "weather",
+    ] {
+        transaction.execute(
+            &format!("DELETE FROM {} WHERE session_id = ?1", table),
+            [id],
+        )?;
+    }
+    let id = Value::Integer(id);
+    for row in rows {
+        transaction
+            .prepare_cached(row.sql)?
+            .execute(params_from_iter(std::iter::once(&id).chain(&row.values)))?;
+    }
+    Ok(())
+}
+
+/// Saves snapshots on a thread of its own so saving never holds up the
+/// caller, saves of the same `generation` update one session row
+pub struct Writer {
+    sender: Option<mpsc::Sender<(u64, Snapshot)>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Writer {
+    pub fn spawn(mut archive: Archive) -> Self {
+        let (sender, receiver) = mpsc::channel::<(u64, Snapshot)>();
+        let thread = thread::spawn(move || {
+            let mut current: Option<(u64, i64)> = None;
+            for (generation, snapshot) in receiver {
+                let id = current
+                    .filter(|(saved, _)| *saved == generation)
+                    .map(|(_, id)| id);
+                match archive.write(id, &snapshot) {
+                    Ok(id) => current = Some((generation, id)),
+                    Err(e) => error!("could not archive session: {}", e),
+                }
+            }
+        });
+        Writer {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn save(&self, generation: u64, snapshot: Snapshot) {
+        if let Some(sender) = &self.sender {
+            if sender.send((generation, snapshot)).is_err() {
+                error!("archive writer stopped, session not saved");
+            }
+        }
+    }
+}
+
+impl Drop for Writer {
+    /// waits for the saves still queued
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export, fixture, replay};
+
+    /// a fresh archive file in the temp directory
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("backmarker-{}-{}.sqlite", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let session = replay::replayed(&fixture::short_race());
+
+        let path = temp_path("round-trip");
+        let mut archive = Archive::open_path(&path).unwrap();
+        let id = archive.save(None, &session).unwrap();
+        // saving the same session again updates its row
+        assert_eq!(archive.save(Some(id), &session).unwrap(), id);
+        let sessions = archive.sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].cars, 4);
+
+        let loaded = archive.load(id).unwrap();
+        drop(archive);
+        std::fs::remove_file(&path).unwrap();
+        let gaps = |session: &Session| {
+            export::collect(session)
+                .results
+                .into_iter()
+                .map(|row| row.gap)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(gaps(&loaded), ["-", "+2.5", "+25.5", "+1 lap"]);
+        // the gaps are the only thing measured from track positions
+        let json = |session: &Session| {
+            let mut export = export::collect(session);
+            for row in &mut export.results {
+                row.gap.clear();
+            }
+            serde_json::to_string_pretty(&export).unwrap()
+        };
+        assert_eq!(json(&loaded), json(&session));
+    }
+
+    #[test]
+    fn writer_updates_one_row_per_generation() {
+        let session = replay::replayed(&fixture::short_race());
+
+        let path = temp_path("writer");
+        let archive = Archive::open_path(&path).unwrap();
+        let writer = Writer::spawn(archive.reopen().unwrap());
+        for generation in [0, 0, 1] {
+            writer.save(generation, Snapshot::take(&session).unwrap());
+        }
+        drop(writer);
+        assert_eq!(archive.sessions().unwrap().len(), 2);
+        drop(archive);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//! Module for UDP capture files
+//!
+//! Stores the raw broadcasting packets with their arrival time so a session
+//! can be fed through the pipeline again later.
+//!
+//! File format, all numbers little endian:
+//! - header: `MAGIC` followed by the format version as u16
+//! - per packet: ms since the recording started as u32, the packet length
+//!   as u16 and the packet bytes
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+pub const MAGIC: &[u8; 8] = b"BMKRCAP\0";
+pub const VERSION: u16 = 1;
+/// longest time recorded packets stay in the write buffer
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One recorded packet
+#[derive(Debug, Clone)]
+pub struct Packet {
+    /// ms since the recording started
+    pub at_ms: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// reads every packet of a capture file, a packet cut off at the end of
+/// the file is dropped
+pub fn read(path: &Path) -> Result<Vec<Packet>, String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let header = MAGIC.len() + 2;
+    if data.len() < header || &data[..MAGIC.len()] != MAGIC {
+        return Err(format!("{} is not a capture file", path.display()));
+    }
+    let version = u16::from_le_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]);
+    if version != VERSION {
+        return Err(format!("unsupported capture version {}", version));
+    }
+
+    let mut packets = vec![];
+    let mut rest = &data[header..];
+    while !rest.is_empty() {
+        let Some((at, len)) = rest.get(..4).zip(rest.get(4..6)) else {
+            warn!("capture ends inside a packet header");
+            break;
+        };
+        let at_ms = u32::from_le_bytes([at[0], at[1], at[2], at[3]]);
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let Some(bytes) = rest.get(6..6 + len) else {
+            warn!("capture ends inside a packet");
+            break;
+        };
+        packets.push(Packet {
+            at_ms,
+            bytes: bytes.to_vec(),
+        });
+        rest = &rest[6 + len..];
+    }
+    Ok(packets)
+}
+
+pub struct Recorder {
+    /// shared with the Ctrl+C handler
+    writer: Arc<Mutex<BufWriter<File>>>,
+    started: Instant,
+    last_flush: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC).map_err(|e| e.to_string())?;
+        writer
+            .write_all(&VERSION.to_le_bytes())
+            .map_err(|e| e.to_string())?;
+        info!("recording packets to {}", path.display());
+        Ok(Recorder {
+            writer: Arc::new(Mutex::new(writer)),
+            started: Instant::now(),
+            last_flush: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, packet: &[u8]) -> Result<(), String> {
+        let at = self.started.elapsed().as_millis() as u32;
+        let len = u16::try_from(packet.len()).map_err(|_| String::from("packet too large"))?;
+        let mut writer = self.writer.lock().map_err(|e| e.to_string())?;
+        writer
+            .write_all(&at.to_le_bytes())
+            .and_then(|_| writer.write_all(&len.to_le_bytes()))
+            .and_then(|_| writer.write_all(packet))
+            .map_err(|e| e.to_string())?;
+        // keep the file usable if the process gets killed
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            writer.flush().map_err(|e| e.to_string())?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// flushes the recording and exits on Ctrl+C, the write buffer is not
+    /// flushed while no packets arrive
+    pub fn flush_on_interrupt(&self) -> Result<(), String> {
+        let writer = Arc::clone(&self.writer);
+        ctrlc::set_handler(move || {
+            if let Ok(mut writer) = writer.lock() {
+                if let Err(e) = writer.flush() {
+                    eprintln!("could not flush recording: {}", e);
+                }
+            }
+            std::process::exit(130);
+        })
+        .map_err(|e| format!("could not set Ctrl+C handler: {}", e))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Module for the ACC car model catalog
+//!
+//! Maps the raw `CarInfo::car_model_type` id to the car's name,
+//! manufacturer, class and model year.
+
+use std::fmt;
+
+use CarClass::{Challenge, Cup, Gt2, Gt3, Gt4, SuperTrofeo, Tcx};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CarClass {
+    Gt3,
+    Gt2,
+    Gt4,
+    Cup,
+    SuperTrofeo,
+    Challenge,
+    Tcx,
+}
+
+impl CarClass {
+    pub const ALL: [CarClass; 7] = [
+        CarClass::Gt3,
+        CarClass::Gt2,
+        CarClass::Gt4,
+        CarClass::Cup,
+        CarClass::SuperTrofeo,
+        CarClass::Challenge,
+        CarClass::Tcx,
+    ];
+
+    /// class of an ACC `car_model_type`, unknown ids are guessed from the id range
+    pub fn from_model(car_model_type: u8) -> Self {
+        match lookup(car_model_type) {
+            Some(model) => model.class,
+            None => match car_model_type {
+                50..=79 => CarClass::Gt4,
+                80..=99 => CarClass::Gt2,
+                _ => CarClass::Gt3,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CarClass::Gt3 => "GT3",
+            CarClass::Gt2 => "GT2",
+            CarClass::Gt4 => "GT4",
+            CarClass::Cup => "CUP",
+            CarClass::SuperTrofeo => "ST",
+            CarClass::Challenge => "CHL",
+            CarClass::Tcx => "TCX",
+        }
+    }
+
+    /// display color as rgb
+    pub fn rgb(&self) -> (f32, f32, f32) {
+        match self {
+            CarClass::Gt3 => (0.9, 0.2, 0.2),
+            CarClass::Gt2 => (0.6, 0.2, 0.8),
+            CarClass::Gt4 => (0.2, 0.5, 0.9),
+            CarClass::Cup => (0.2, 0.7, 0.3),
+            CarClass::SuperTrofeo => (0.9, 0.8, 0.1),
+            CarClass::Challenge => (0.9, 0.5, 0.6),
+            CarClass::Tcx => (0.3, 0.8, 0.8),
+        }
+    }
+}
+
+impl fmt::Display for CarClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct CarModel {
+    pub id: u8,
+    pub name: &'static str,
+    pub manufacturer: &'static str,
+    pub class: CarClass,
+    pub year: u16,
+}
+
+const fn car(
+    id: u8,
+    name: &'static str,
+    manufacturer: &'static str,
+    class: CarClass,
+    year: u16,
+) -> CarModel {
+    CarModel {
+        id,
+        name,
+        manufacturer,
+        class,
+        year,
+    }
+}
+
+#[rustfmt::skip]
+pub const MODELS: &[CarModel] = &[
+    car(0, "Porsche 991 GT3 R", "Porsche", Gt3, 2018),
+    car(1, "Mercedes-AMG GT3", "Mercedes-AMG", Gt3, 2015),
+    car(2, "Ferrari 488 GT3", "Ferrari", Gt3, 2018),
+    car(3, "Audi R8 LMS", "Audi", Gt3, 2015),
+    car(4, "Lamborghini Huracan GT3", "Lamborghini", Gt3, 2015),
+    car(5, "McLaren 650S GT3", "McLaren", Gt3, 2015),
+    car(6, "Nissan GT-R Nismo GT3", "Nissan", Gt3, 2018),
+    car(7, "BMW M6 GT3", "BMW", Gt3, 2017),
+    car(8, "Bentley Continental GT3", "Bentley", Gt3, 2018),
+    car(9, "Porsche 991 II GT3 Cup", "Porsche", Cup, 2017),
+    car(10, "Nissan GT-R Nismo GT3", "Nissan", Gt3, 2015),
+    car(11, "Bentley Continental GT3", "Bentley", Gt3, 2015),
+    car(12, "Aston Martin V12 Vantage GT3", "Aston Martin", Gt3, 2013),
+    car(13, "Reiter Engineering R-EX GT3", "Reiter Engineering", Gt3, 2017),
+    car(14, "Emil Frey Jaguar G3", "Jaguar", Gt3, 2012),
+    car(15, "Lexus RC F GT3", "Lexus", Gt3, 2016),
+    car(16, "Lamborghini Huracan GT3 Evo", "Lamborghini", Gt3, 2019),
+    car(17, "Honda NSX GT3", "Honda", Gt3, 2017),
+    car(18, "Lamborghini Huracan Super Trofeo", "Lamborghini", SuperTrofeo, 2015),
+    car(19, "Audi R8 LMS Evo", "Audi", Gt3, 2019),
+    car(20, "Aston Martin V8 Vantage GT3", "Aston Martin", Gt3, 2019),
+    car(21, "Honda NSX GT3 Evo", "Honda", Gt3, 2019),
+    car(22, "McLaren 720S GT3", "McLaren", Gt3, 2019),
+    car(23, "Porsche 991 II GT3 R", "Porsche", Gt3, 2019),
+    car(24, "Ferrari 488 GT3 Evo", "Ferrari", Gt3, 2020),
+    car(25, "Mercedes-AMG GT3 Evo", "Mercedes-AMG", Gt3, 2020),
+    car(26, "Ferrari 488 Challenge Evo", "Ferrari", Challenge, 2020),
+    car(27, "BMW M2 CS Racing", "BMW", Tcx, 2020),
+    car(28, "Porsche 992 GT3 Cup", "Porsche", Cup, 2021),
+    car(29, "Lamborghini Huracan Super Trofeo EVO2", "Lamborghini", SuperTrofeo, 2021),
+    car(30, "BMW M4 GT3", "BMW", Gt3, 2021),
+    car(31, "Audi R8 LMS GT3 Evo II", "Audi", Gt3, 2022),
+    car(32, "Ferrari 296 GT3", "Ferrari", Gt3, 2023),
+    car(33, "Lamborghini Huracan GT3 Evo2", "Lamborghini", Gt3, 2023),
+    car(34, "Porsche 992 GT3 R", "Porsche", Gt3, 2023),
+    car(35, "McLaren 720S GT3 Evo", "McLaren", Gt3, 2023),
+    car(36, "Ford Mustang GT3", "Ford", Gt3, 2024),
+    car(50, "Alpine A110 GT4", "Alpine", Gt4, 2018),
+    car(51, "Aston Martin V8 Vantage GT4", "Aston Martin", Gt4, 2018),
+    car(52, "Audi R8 LMS GT4", "Audi", Gt4, 2018),
+    car(53, "BMW M4 GT4", "BMW", Gt4, 2018),
+    car(55, "Chevrolet Camaro GT4.R", "Chevrolet", Gt4, 2017),
+    car(56, "Ginetta G55 GT4", "Ginetta", Gt4, 2012),
+    car(57, "KTM X-Bow GT4", "KTM", Gt4, 2016),
+    car(58, "Maserati MC GT4", "Maserati", Gt4, 2016),
+    car(59, "McLaren 570S GT4", "McLaren", Gt4, 2016),
+    car(60, "Mercedes-AMG GT4", "Mercedes-AMG", Gt4, 2016),
+    car(61, "Porsche 718 Cayman GT4 Clubsport", "Porsche", Gt4, 2019),
+    car(80, "Audi R8 LMS GT2", "Audi", Gt2, 2021),
+    car(82, "KTM X-Bow GT2", "KTM", Gt2, 2021),
+    car(83, "Maserati MC20 GT2", "Maserati", Gt2, 2023),
+    car(84, "Mercedes-AMG GT2", "Mercedes-AMG", Gt2, 2023),
+    car(85, "Porsche 911 GT2 RS CS Evo", "Porsche", Gt2, 2023),
+    car(86, "Porsche 935", "Porsche", Gt2, 2019),
+];
+
+pub fn lookup(car_model_type: u8) -> Option<&'static CarModel> {
+    MODELS.iter().find(|model| model.id == car_model_type)
+}
+
+/// display name of a model, unknown ids get a placeholder instead
+pub fn name(car_model_type: u8) -> String {
+    match lookup(car_model_type) {
+        Some(model) => model.name.to_string(),
+        None => format!("unknown car ({})", car_model_type),
+    }
+}
@@ -0,0 +1,120 @@
+//! Module for multi-class classification
+//!
+//! Splits the official order into car classes, giving every car its class
+//! position and the gaps to the overall and class leaders.
+
+use std::collections::HashMap;
+
+use crate::{
+    car_models::CarClass,
+    session::{Car, Session},
+    udp::CarLocation,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gap {
+    Leader,
+    /// seconds behind
+    Time(f32),
+    /// whole laps behind
+    Laps(u16),
+}
+
+impl Gap {
+    /// gap from `leader` back to `car`, measured in `car`'s pace
+    pub fn between(leader: &Car, car: &Car) -> Self {
+        if leader.car_info.car_index == car.car_info.car_index {
+            return Gap::Leader;
+        }
+        let laps = (leader.distance() - car.distance()).max(0.0);
+        if laps >= 1.0 {
+            return Gap::Laps(laps.floor() as u16);
+        }
+        // archived cars have no track position, their last crossing of the
+        // line is the best there is
+        if car.location() == CarLocation::None || leader.location() == CarLocation::None {
+            if let (Some(lap), Some(leader_lap)) = (car.laps.last(), leader.laps.last()) {
+                return Gap::Time((lap.completed_at - leader_lap.completed_at).max(0.0) / 1000.0);
+            }
+        }
+        match car.rolling_pace().or(leader.rolling_pace()) {
+            Some(pace) => Gap::Time(laps * pace / 1000.0),
+            None => Gap::Time(0.0),
+        }
+    }
+}
+
+impl std::fmt::Display for Gap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gap::Leader => write!(f, "-"),
+            Gap::Time(seconds) => write!(f, "+{:.1}", seconds),
+            Gap::Laps(1) => write!(f, "+1 lap"),
+            Gap::Laps(laps) => write!(f, "+{} laps", laps),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    Overall,
+    /// grouped by class, each class in class position order
+    Class,
+}
+
+#[derive(Debug)]
+pub struct Classified<'a> {
+    pub car: &'a Car,
+    pub class: CarClass,
+    pub class_position: u16,
+    pub gap_to_leader: Gap,
+    pub gap_to_class_leader: Gap,
+}
+
+/// every car in official order with its class position and gaps
+pub fn classify(session: &Session) -> Vec<Classified<'_>> {
+    let standings = session.standings();
+    let Some(leader) = standings.first().copied() else {
+        return vec![];
+    };
+
+    let mut class_leaders: HashMap<CarClass, &Car> = HashMap::new();
+    let mut class_counts: HashMap<CarClass, u16> = HashMap::new();
+    standings
+        .into_iter()
+        .map(|car| {
+            let class = car.class();
+            let class_leader = *class_leaders.entry(class).or_insert(car);
+            let class_position = class_counts.entry(class).or_insert(0);
+            *class_position += 1;
+            Classified {
+                car,
+                class,
+                class_position: *class_position,
+                gap_to_leader: Gap::between(leader, car),
+                gap_to_class_leader: Gap::between(class_leader, car),
+            }
+        })
+        .collect()
+}
+
+/// classification in the requested order, optionally limited to one class
+pub fn ordered(session: &Session, order: Order, filter: Option<CarClass>) -> Vec<Classified<'_>> {
+    let mut classified: Vec<Classified> = classify(session)
+        .into_iter()
+        .filter(|entry| filter.is_none_or(|class| entry.class == class))
+        .collect();
+    if order == Order::Class {
+        // stable sort keeps class position order inside each class
+        classified.sort_by_key(|entry| entry.class);
+    }
+    classified
+}
+
+/// classes present in the session
+pub fn classes(session: &Session) -> Vec<CarClass> {
+    let mut classes: Vec<CarClass> = session.cars.values().map(|car| car.class()).collect();
+    classes.sort();
+    classes.dedup();
+    classes
+}
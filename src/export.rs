@@ -0,0 +1,342 @@
+//! Module for exporting session data
+//!
+//! Writes results, laps, stints and pit stops as CSV files plus one JSON
+//! file with everything, for post processing outside of backmarker.
+//!
+//! The schema is stable: columns keep their names and order, new columns
+//! are only ever appended and `SCHEMA_VERSION` goes up when they are.
+//! Times are in ms, positions are 1 based and 0 when unknown.
+//!
+//! - `results.csv`: `ResultRow`, one row per car in finishing order
+//! - `laps.csv`: `LapRow`, one row per completed lap
+//! - `stints.csv`: `StintRow`, one row per stint
+//! - `pit_stops.csv`: `PitStopRow`, one row per pit lane visit
+//! - `session.json`: `SessionExport`, the header and all four tables
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    classification,
+    session::{Car, Session},
+    udp, utils,
+};
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResultRow {
+    pub position: u16,
+    pub class_position: u16,
+    pub car_index: u16,
+    pub race_number: u32,
+    pub team: String,
+    pub class: &'static str,
+    pub model: String,
+    /// driver names separated by " / "
+    pub drivers: String,
+    pub laps: u16,
+    pub best_lap_ms: Option<u32>,
+    /// session time the last lap was completed at
+    pub finish_time_ms: Option<f32>,
+    /// "-" for the leader, "+12.3" seconds or "+2 laps" behind
+    pub gap: String,
+    pub pit_stops: usize,
+    pub penalties: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LapRow {
+    pub car_index: u16,
+    pub race_number: u32,
+    pub lap: u16,
+    pub driver_index: u16,
+    pub driver: String,
+    pub laptime_ms: u32,
+    pub sector1_ms: Option<u32>,
+    pub sector2_ms: Option<u32>,
+    pub sector3_ms: Option<u32>,
+    pub valid: bool,
+    /// "regular", "inlap" or "outlap"
+    pub lap_type: &'static str,
+    pub position: u16,
+    /// position inside the cup category, as sent by ACC
+    pub cup_position: u16,
+    pub completed_at_ms: f32,
+    /// driven at least partly under safety car or full course yellow
+    pub neutralized: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct StintRow {
+    pub car_index: u16,
+    pub race_number: u32,
+    /// 1 based
+    pub stint: usize,
+    pub first_lap: u16,
+    pub last_lap: u16,
+    pub laps: u16,
+    pub best_lap_ms: Option<u32>,
+    /// average over the clean laps of the stint
+    pub average_lap_ms: Option<f32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PitStopRow {
+    pub car_index: u16,
+    pub race_number: u32,
+    /// 1 based
+    pub stop: usize,
+    pub lap: u16,
+    pub entry_time_ms: f32,
+    pub exit_time_ms: Option<f32>,
+    pub lane_time_ms: Option<f32>,
+    pub stationary_ms: Option<f32>,
+    /// passed through the pit lane without stopping
+    pub drive_through: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionExport {
+    pub schema_version: u32,
+    pub track: Option<String>,
+    pub session_type: Option<String>,
+    pub session_time_ms: f32,
+    pub results: Vec<ResultRow>,
+    pub laps: Vec<LapRow>,
+    pub stints: Vec<StintRow>,
+    pub pit_stops: Vec<PitStopRow>,
+}
+
+fn lap_type(lap_type: udp::LapType) -> &'static str {
+    match lap_type {
+        udp::LapType::Regular => "regular",
+        udp::LapType::Inlap => "inlap",
+        udp::LapType::Outlap => "outlap",
+    }
+}
+
+/// a split or lap time, `None` for the values ACC sends for no time
+fn time(ms: u32) -> Option<u32> {
+    Some(ms).filter(|ms| *ms > 0 && *ms < i32::MAX as u32)
+}
+
+fn driver_name(car: &Car, index: u16) -> String {
+    car.car_info
+        .drivers
+        .get(index as usize)
+        .map_or(String::new(), |driver| {
+            format!("{} {}", driver.first_name, driver.last_name)
+        })
+}
+
+fn best_lap(car: &Car, first_lap: u16, last_lap: u16) -> Option<u32> {
+    car.laps
+        .iter()
+        .filter(|lap| (first_lap..=last_lap).contains(&lap.number) && !lap.info.is_invalid)
+        .filter_map(|lap| time(lap.info.laptime_ms))
+        .min()
+}
+
+/// collects the export tables, cars in classification order
+pub fn collect(session: &Session) -> SessionExport {
+    let classified = classification::classify(session);
+    let mut export = SessionExport {
+        schema_version: SCHEMA_VERSION,
+        track: session.track.as_ref().map(|track| track.track_name.clone()),
+        session_type: session
+            .realtime
+            .as_ref()
+            .map(|update| format!("{:?}", update.session_type)),
+        session_time_ms: session.session_time(),
+        results: vec![],
+        laps: vec![],
+        stints: vec![],
+        pit_stops: vec![],
+    };
+
+    for entry in &classified {
+        let car = entry.car;
+        let (car_index, race_number) = (car.car_info.car_index, car.car_info.race_number);
+        export.results.push(ResultRow {
+            position: car.position(),
+            class_position: entry.class_position,
+            car_index,
+            race_number,
+            team: car.car_info.team_name.clone(),
+            class: entry.class.name(),
+            model: car.model_name(),
+            drivers: (0..car.car_info.drivers.len() as u16)
+                .map(|index| driver_name(car, index))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            laps: car.lap_count(),
+            best_lap_ms: best_lap(car, 0, u16::MAX),
+            finish_time_ms: car.laps.last().map(|lap| lap.completed_at),
+            gap: entry.gap_to_leader.to_string(),
+            pit_stops: car.stops().count(),
+            penalties: car.penalties.len(),
+        });
+
+        export.laps.extend(car.laps.iter().map(|lap| {
+            let split = |sector: usize| lap.info.lap_splits.get(sector).copied().and_then(time);
+            LapRow {
+                car_index,
+                race_number,
+                lap: lap.number,
+                driver_index: lap.info.driver_index,
+                driver: driver_name(car, lap.info.driver_index),
+                laptime_ms: lap.info.laptime_ms,
+                sector1_ms: split(0),
+                sector2_ms: split(1),
+                sector3_ms: split(2),
+                valid: !lap.info.is_invalid,
+                lap_type: lap_type(lap.info.lap_type),
+                position: lap.position,
+                cup_position: lap.cup_position,
+                completed_at_ms: lap.completed_at,
+                neutralized: lap.neutralized,
+            }
+        }));
+
+        export
+            .stints
+            .extend(
+                car.stints()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(number, (first_lap, last_lap))| {
+                        let clean: Vec<f32> = car
+                            .clean_laps()
+                            .filter(|lap| (first_lap..=last_lap).contains(&lap.number))
+                            .map(|lap| lap.info.laptime_ms as f32)
+                            .collect();
+                        StintRow {
+                            car_index,
+                            race_number,
+                            stint: number + 1,
+                            first_lap,
+                            last_lap,
+                            laps: last_lap - first_lap + 1,
+                            best_lap_ms: best_lap(car, first_lap, last_lap),
+                            average_lap_ms: (!clean.is_empty())
+                                .then(|| clean.iter().sum::<f32>() / clean.len() as f32),
+                        }
+                    }),
+            );
+
+        export
+            .pit_stops
+            .extend(
+                car.pit_stops
+                    .iter()
+                    .enumerate()
+                    .map(|(number, stop)| PitStopRow {
+                        car_index,
+                        race_number,
+                        stop: number + 1,
+                        lap: stop.lap,
+                        entry_time_ms: stop.entry_time,
+                        exit_time_ms: stop.exit_time,
+                        lane_time_ms: stop.lane_time(),
+                        stationary_ms: stop.stationary_ms,
+                        drive_through: stop.exit_time.is_some() && !stop.is_stop(),
+                    }),
+            );
+    }
+    export
+}
+
+fn write_csv<T: Serialize + Default>(path: &Path, rows: &[T]) -> Result<(), String> {
+    if rows.is_empty() {
+        // the header comes from the first row, borrow it from a blank one
+        let mut header = csv::Writer::from_writer(vec![]);
+        header.serialize(T::default()).map_err(|e| e.to_string())?;
+        let bytes = header.into_inner().map_err(|e| e.to_string())?;
+        let end = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(bytes.len(), |i| i + 1);
+        return fs::write(path, &bytes[..end]).map_err(|e| e.to_string());
+    }
+    let mut writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    for row in rows {
+        writer.serialize(row).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// fresh directory below the data directory named after the session
+pub fn default_dir(session: &Session) -> PathBuf {
+    let track = session
+        .track
+        .as_ref()
+        .map_or(String::from("unknown"), |track| {
+            track.track_name.replace(' ', "_")
+        });
+    let session_type = session
+        .realtime
+        .as_ref()
+        .map_or(String::from("session"), |update| {
+            format!("{:?}", update.session_type).to_lowercase()
+        });
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    utils::data_dir()
+        .join("exports")
+        .join(format!("{}-{}-{}", track, session_type, now))
+}
+
+/// writes the CSV files and `session.json` into `dir`
+pub fn write(session: &Session, dir: &Path) -> Result<(), String> {
+    write_export(&collect(session), dir)
+}
+
+/// writes collected tables, for writing away from the session
+pub fn write_export(export: &SessionExport, dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    write_csv(&dir.join("results.csv"), &export.results)?;
+    write_csv(&dir.join("laps.csv"), &export.laps)?;
+    write_csv(&dir.join("stints.csv"), &export.stints)?;
+    write_csv(&dir.join("pit_stops.csv"), &export.pit_stops)?;
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    fs::write(dir.join("session.json"), json).map_err(|e| e.to_string())?;
+    info!("exported session to {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, replay};
+
+    #[test]
+    fn short_race_export() {
+        let session = replay::replayed(&fixture::short_race());
+        let export = collect(&session);
+
+        let finishers: Vec<u32> = export.results.iter().map(|row| row.race_number).collect();
+        assert_eq!(finishers, [99, 7, 22, 46]);
+        let stints: Vec<(u16, u16)> = export
+            .stints
+            .iter()
+            .filter(|row| row.race_number == 22)
+            .map(|row| (row.first_lap, row.last_lap))
+            .collect();
+        assert_eq!(stints, [(1, 2), (3, 3)]);
+        let stop = &export.pit_stops[0];
+        // laps completed when it entered the pit lane
+        assert_eq!((stop.race_number, stop.lap), (22, 1));
+        assert!(!stop.drive_through);
+
+        let json = serde_json::to_string_pretty(&export).unwrap();
+        fixture::snapshot("short_race.json", &json);
+    }
+}
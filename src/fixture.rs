@@ -0,0 +1,438 @@
+//! Test fixture: a short scripted race as broadcasting packets
+//!
+//! Encodes the packets ACC would send for a five minute race with three
+//! GT3 cars and one GT4, so tests can feed a capture through the same path
+//! as a live session. Our car is #7. #99 is the fastest and passes it on
+//! the first lap, #22 makes a pit stop at the end of lap 2 and #46 stops
+//! on track for a while on lap 2.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::capture::{self, Packet};
+
+/// time between two updates in ms
+const TICK_MS: u32 = 500;
+pub const RACE_MS: u32 = 300_000;
+const TRACK_METERS: u32 = 5_000;
+
+/// Little endian packet writer in the broadcasting protocol layout
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new(message_type: u8) -> Self {
+        Encoder {
+            bytes: vec![message_type],
+        }
+    }
+
+    pub fn u8(mut self, value: u8) -> Self {
+        self.bytes.push(value);
+        self
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.bytes.extend(value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.bytes.extend(value.to_le_bytes());
+        self
+    }
+
+    pub fn f32(mut self, value: f32) -> Self {
+        self.bytes.extend(value.to_le_bytes());
+        self
+    }
+
+    pub fn string(self, value: &str) -> Self {
+        let mut encoder = self.u16(value.len() as u16);
+        encoder.bytes.extend(value.as_bytes());
+        encoder
+    }
+
+    /// lap info as in realtime and car updates, `kind` 1 out lap, 2 in lap
+    pub fn lap(self, laptime_ms: u32, car_index: u16, splits: &[u32], kind: u8) -> Self {
+        let mut encoder = self
+            .u32(laptime_ms)
+            .u16(car_index)
+            .u16(0)
+            .u8(splits.len() as u8);
+        for split in splits {
+            encoder = encoder.u32(*split);
+        }
+        encoder
+            .u8(0)
+            .u8(1)
+            .u8((kind == 1) as u8)
+            .u8((kind == 2) as u8)
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+fn registration_result() -> Vec<u8> {
+    Encoder::new(1).u32(1).u8(1).u8(0).finish()
+}
+
+fn track_data() -> Vec<u8> {
+    Encoder::new(5)
+        .u32(1)
+        .string("Test Ring")
+        .u32(9_999)
+        .u32(TRACK_METERS)
+        .u8(0)
+        .u8(0)
+        .finish()
+}
+
+fn entry_list(cars: &[Car]) -> Vec<u8> {
+    let mut encoder = Encoder::new(4).u32(1).u16(cars.len() as u16);
+    for car in cars {
+        encoder = encoder.u16(car.index);
+    }
+    encoder.finish()
+}
+
+fn entry_list_car(car: &Car) -> Vec<u8> {
+    let (first_name, last_name) = car.driver;
+    Encoder::new(6)
+        .u16(car.index)
+        .u8(car.model)
+        .string(car.team)
+        .u32(car.number)
+        .u8(0)
+        .u8(0)
+        .u16(0)
+        .u8(1)
+        .string(first_name)
+        .string(last_name)
+        .string(&last_name[..3].to_uppercase())
+        .u8(0)
+        .u16(0)
+        .finish()
+}
+
+fn broadcasting_event(event_type: u8, msg: &str, time_ms: u32, car_index: u32) -> Vec<u8> {
+    Encoder::new(7)
+        .u8(event_type)
+        .string(msg)
+        .u32(time_ms)
+        .u32(car_index)
+        .finish()
+}
+
+/// One scripted car
+struct Car {
+    index: u16,
+    number: u32,
+    model: u8,
+    team: &'static str,
+    driver: (&'static str, &'static str),
+    /// distance at the start, in laps
+    start: f32,
+    pace_ms: f32,
+    /// distance the car stops at and for how long in ms
+    stop: Option<(f32, f32)>,
+    /// distance range driven in the pit lane
+    pit_lane: Option<(f32, f32)>,
+}
+
+impl Car {
+    /// (distance, kmh, in the pit lane) at session time `t`
+    fn state(&self, t: f32) -> (f32, u16, bool) {
+        let mut distance = self.start + t / self.pace_ms;
+        let mut stationary = false;
+        if let Some((at, duration)) = self.stop {
+            let stop_time = (at - self.start) * self.pace_ms;
+            if t >= stop_time + duration {
+                distance = self.start + (t - duration) / self.pace_ms;
+            } else if t >= stop_time {
+                distance = at;
+                stationary = true;
+            }
+        }
+        let in_pits = self
+            .pit_lane
+            .is_some_and(|(from, to)| (from..to).contains(&distance));
+        let kmh = if stationary {
+            0
+        } else if in_pits {
+            60
+        } else {
+            (TRACK_METERS as f32 / self.pace_ms * 3_600.0) as u16
+        };
+        (distance, kmh, in_pits)
+    }
+
+    /// lap type of lap `number`, 1 out lap and 2 in lap
+    fn lap_kind(&self, number: u16) -> u8 {
+        let lap = (number - 1) as f32..number as f32;
+        match self.pit_lane {
+            Some((from, _)) if lap.contains(&from) => 2,
+            Some((_, to)) if lap.contains(&to) => 1,
+            _ => 0,
+        }
+    }
+}
+
+fn cars() -> Vec<Car> {
+    vec![
+        Car {
+            index: 0,
+            number: 7,
+            model: 22,
+            team: "Backmarker Racing",
+            driver: ("Alex", "Marsh"),
+            start: 0.008,
+            pace_ms: 90_000.0,
+            stop: None,
+            pit_lane: None,
+        },
+        Car {
+            index: 1,
+            number: 22,
+            model: 25,
+            team: "Silver Arrow Motorsport",
+            driver: ("Kim", "Larsen"),
+            start: 0.012,
+            pace_ms: 89_800.0,
+            stop: Some((1.99, 24_000.0)),
+            pit_lane: Some((1.95, 2.03)),
+        },
+        Car {
+            index: 2,
+            number: 99,
+            model: 24,
+            team: "Rosso Corse",
+            driver: ("Sam", "Ferro"),
+            start: 0.004,
+            pace_ms: 89_000.0,
+            stop: None,
+            pit_lane: None,
+        },
+        Car {
+            index: 3,
+            number: 46,
+            model: 50,
+            team: "Tin Top Racing",
+            driver: ("Robin", "Keller"),
+            start: 0.0,
+            pace_ms: 100_000.0,
+            stop: Some((1.4, 80_000.0)),
+            pit_lane: None,
+        },
+    ]
+}
+
+/// What the script remembers about a car between updates
+#[derive(Default)]
+struct Progress {
+    laps: u16,
+    sector: usize,
+    sector_start: u32,
+    lap_start: u32,
+    splits: Vec<u32>,
+    /// (laptime, splits, kind) of the last completed lap
+    last: Option<(u32, Vec<u32>, u8)>,
+    best: Option<u32>,
+}
+
+fn car_update(
+    t: u32,
+    car: &Car,
+    progress: &Progress,
+    (distance, kmh, in_pits): (f32, u16, bool),
+    positions: (u16, u16, u16),
+) -> Vec<u8> {
+    let spline = distance.fract();
+    let angle = spline * std::f32::consts::TAU;
+    let (position, cup_position, track_position) = positions;
+    let mut current = progress.splits.clone();
+    current.resize(3, 0);
+    let encoder = Encoder::new(3)
+        .u16(car.index)
+        .u16(0)
+        .u8(1)
+        .u8(if kmh == 0 { 1 } else { 5 })
+        .f32(angle.cos() * 500.0)
+        .f32(angle.sin() * 500.0)
+        .f32(angle)
+        .u8(if in_pits { 2 } else { 1 })
+        .u16(kmh)
+        .u16(position)
+        .u16(cup_position)
+        .u16(track_position)
+        .f32(spline)
+        .u16(progress.laps)
+        .u32(0)
+        .lap(
+            progress.best.unwrap_or(i32::MAX as u32),
+            car.index,
+            &[0, 0, 0],
+            0,
+        );
+    let encoder = match &progress.last {
+        Some((laptime, splits, kind)) => encoder.lap(*laptime, car.index, splits, *kind),
+        None => encoder.lap(i32::MAX as u32, car.index, &[], 0),
+    };
+    encoder
+        .lap(t - progress.lap_start, car.index, &current, 0)
+        .finish()
+}
+
+fn realtime_update(phase: u8, t: u32, best: Option<(u32, u16)>) -> Vec<u8> {
+    let encoder = Encoder::new(2)
+        .u16(0)
+        .u16(0)
+        .u8(10)
+        .u8(phase)
+        .f32(t as f32)
+        .f32(RACE_MS as f32)
+        .u32(0)
+        .string("set1")
+        .string("cam1")
+        .string("Basic HUD")
+        .u8(0)
+        .f32(50_400_000.0)
+        .u8(22)
+        .u8(30)
+        .u8(1)
+        .u8(0)
+        .u8(0);
+    match best {
+        Some((laptime, car_index)) => encoder.lap(laptime, car_index, &[0, 0, 0], 0),
+        None => encoder.lap(i32::MAX as u32, 0, &[], 0),
+    }
+    .finish()
+}
+
+/// rank of every entry of `keys`, highest key first, ties by input order
+fn ranks(keys: &[f32]) -> Vec<u16> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|a, b| keys[*b].total_cmp(&keys[*a]));
+    let mut ranks = vec![0; keys.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        ranks[index] = rank as u16 + 1;
+    }
+    ranks
+}
+
+/// every packet of the race in the order ACC would send them
+pub fn short_race() -> Vec<Packet> {
+    let cars = cars();
+    let mut packets = vec![];
+    let mut push = |at_ms: u32, bytes: Vec<u8>| packets.push(Packet { at_ms, bytes });
+
+    push(0, registration_result());
+    push(0, track_data());
+    push(0, entry_list(&cars));
+    for car in &cars {
+        push(0, entry_list_car(car));
+    }
+    push(0, broadcasting_event(1, "green flag", 0, 0));
+
+    let mut progress: Vec<Progress> = cars.iter().map(|_| Progress::default()).collect();
+    let mut best: Option<(u32, u16)> = None;
+    for t in (0..=RACE_MS).step_by(TICK_MS as usize) {
+        push(t, realtime_update(5, t, best));
+
+        let states: Vec<(f32, u16, bool)> = cars.iter().map(|car| car.state(t as f32)).collect();
+        for (car, (state, progress)) in cars.iter().zip(states.iter().zip(progress.iter_mut())) {
+            let (distance, _, _) = *state;
+            let laps = distance.floor() as u16;
+            let sector = (distance.fract() * 3.0) as usize;
+            if laps > progress.laps || sector != progress.sector {
+                progress.splits.push(t - progress.sector_start);
+                progress.sector_start = t;
+                progress.sector = sector;
+            }
+            if laps > progress.laps {
+                progress.laps = laps;
+                let laptime = t - progress.lap_start;
+                let splits = std::mem::take(&mut progress.splits);
+                progress.last = Some((laptime, splits, car.lap_kind(laps)));
+                progress.lap_start = t;
+                progress.best = Some(progress.best.map_or(laptime, |best| best.min(laptime)));
+                if best.is_none_or(|(best, _)| laptime < best) {
+                    best = Some((laptime, car.index));
+                }
+            }
+        }
+
+        let distances: Vec<f32> = states.iter().map(|(distance, _, _)| *distance).collect();
+        let splines: Vec<f32> = distances.iter().map(|distance| distance.fract()).collect();
+        let positions = ranks(&distances);
+        let track_positions = ranks(&splines);
+        for (i, car) in cars.iter().enumerate() {
+            let class_distances: Vec<f32> = cars
+                .iter()
+                .zip(&distances)
+                .map(|(other, distance)| {
+                    if (other.model >= 50) == (car.model >= 50) {
+                        *distance
+                    } else {
+                        f32::MIN
+                    }
+                })
+                .collect();
+            let cup_position = ranks(&class_distances)[i];
+            push(
+                t,
+                car_update(
+                    t,
+                    car,
+                    &progress[i],
+                    states[i],
+                    (positions[i], cup_position, track_positions[i]),
+                ),
+            );
+        }
+    }
+
+    push(RACE_MS, broadcasting_event(2, "session over", 0, 0));
+    push(RACE_MS + TICK_MS, realtime_update(6, RACE_MS, best));
+    push(RACE_MS + 2 * TICK_MS, realtime_update(8, RACE_MS, best));
+    packets
+}
+
+/// writes `packets` as a capture file in the temp directory
+pub fn write_capture(name: &str, packets: &[Packet]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("backmarker-{}-{}.cap", name, std::process::id()));
+    let mut bytes = capture::MAGIC.to_vec();
+    bytes.extend(capture::VERSION.to_le_bytes());
+    for packet in packets {
+        bytes.extend(packet.at_ms.to_le_bytes());
+        bytes.extend((packet.bytes.len() as u16).to_le_bytes());
+        bytes.extend(&packet.bytes);
+    }
+    fs::write(&path, bytes).expect("could not write test capture");
+    path
+}
+
+/// `testdata/<name>`, rewritten from `actual` when `BLESS` is set
+pub fn snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join(name);
+    if std::env::var_os("BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {}, run with BLESS=1 to create it", path.display(), e));
+    assert_eq!(
+        actual,
+        expected,
+        "{} is out of date, run with BLESS=1 to update it",
+        path.display()
+    );
+}
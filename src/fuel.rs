@@ -0,0 +1,64 @@
+//! Module for fuel tracking
+//!
+//! Follows the fuel level of our car from shared memory and measures how
+//! much every lap uses.
+
+/// laps the usage per lap is averaged over
+const USAGE_WINDOW: usize = 3;
+
+#[derive(Debug, Default)]
+pub struct FuelTracker {
+    /// last reading in liters
+    pub level: Option<f32>,
+    /// level when the current lap started
+    lap_start: Option<f32>,
+    /// liters used on the last laps, newest last
+    usage: Vec<f32>,
+}
+
+impl FuelTracker {
+    pub fn apply_level(&mut self, liters: f32) {
+        if self.lap_start.is_none() {
+            self.lap_start = Some(liters);
+        }
+        self.level = Some(liters);
+    }
+
+    pub fn lap_completed(&mut self) {
+        let Some(level) = self.level else {
+            return;
+        };
+        if let Some(start) = self.lap_start {
+            // a rise means we refuelled, that lap tells nothing
+            if start > level {
+                self.usage.push(start - level);
+                if self.usage.len() > USAGE_WINDOW {
+                    self.usage.remove(0);
+                }
+            }
+        }
+        self.lap_start = Some(level);
+    }
+
+    /// average liters per lap
+    pub fn per_lap(&self) -> Option<f32> {
+        if self.usage.is_empty() {
+            return None;
+        }
+        Some(self.usage.iter().sum::<f32>() / self.usage.len() as f32)
+    }
+
+    /// laps the fuel in the tank lasts at the measured usage
+    pub fn laps_left(&self) -> Option<f32> {
+        Some(self.level? / self.per_lap()?)
+    }
+
+    /// liters missing to cover `laps` more laps, negative with fuel to spare
+    pub fn shortfall(&self, laps: f32) -> Option<f32> {
+        Some(laps * self.per_lap()? - self.level?)
+    }
+
+    pub fn clear(&mut self) {
+        *self = FuelTracker::default();
+    }
+}
@@ -0,0 +1,208 @@
+//! Module for slow and stopped cars
+//!
+//! Learns how fast cars normally go at every part of the lap, flags cars on
+//! track that are stopped or far below that speed as hazards and warns
+//! when our car is heading towards one.
+
+use std::collections::{HashMap, HashSet};
+
+use log::debug;
+
+use crate::{
+    alerts::Alert,
+    pit::spline_delta,
+    session::Car,
+    track::{TrackRecord, BINS},
+    udp,
+};
+
+/// below this a car counts as stopped (km/h)
+const STOPPED_KMH: f32 = 20.0;
+/// share of the usual speed at a spot below which a car counts as slow
+const SLOW_FRACTION: f32 = 0.4;
+/// samples a bin needs before its usual speed is trusted
+const MIN_SAMPLES: u32 = 20;
+/// the usual speed keeps adapting after this many samples
+const MAX_WEIGHT: u32 = 200;
+/// a car has to stay slow this long before it is a hazard (ms)
+const CONFIRM_MS: f32 = 2_000.0;
+/// our driver is warned this many seconds before reaching a hazard
+pub const WARN_S: f32 = 10.0;
+/// half the length of the track flagged around a hazard, share of a lap
+pub const ZONE: f32 = 0.01;
+
+#[derive(Debug, Clone)]
+pub struct Hazard {
+    pub car_index: u16,
+    pub race_number: u32,
+    pub spline_position: f32,
+    /// session time (ms) the car first went slow
+    pub since: f32,
+    pub kmh: u16,
+    pub stopped: bool,
+}
+
+impl Hazard {
+    pub fn describe(&self, record: Option<&TrackRecord>) -> String {
+        let place = match record {
+            Some(record) => record.describe(self.spline_position),
+            None => format!("{:.0}%", self.spline_position * 100.0),
+        };
+        format!(
+            "#{} {} at {}",
+            self.race_number,
+            if self.stopped { "stopped" } else { "slow" },
+            place
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BinSpeed {
+    kmh: f32,
+    samples: u32,
+}
+
+#[derive(Debug)]
+pub struct HazardTracker {
+    /// usual speed at every spline bin
+    profile: Vec<BinSpeed>,
+    /// session time each car went slow at
+    slow_since: HashMap<u16, f32>,
+    /// confirmed hazards
+    pub hazards: Vec<Hazard>,
+    /// hazards our driver was already warned about
+    warned: HashSet<u16>,
+}
+
+impl Default for HazardTracker {
+    fn default() -> Self {
+        HazardTracker {
+            profile: vec![BinSpeed::default(); BINS],
+            slow_since: HashMap::new(),
+            hazards: vec![],
+            warned: HashSet::new(),
+        }
+    }
+}
+
+fn bin(spline_position: f32) -> usize {
+    ((spline_position.rem_euclid(1.0) * BINS as f32) as usize).min(BINS - 1)
+}
+
+impl HazardTracker {
+    /// forgets the current hazards, the learned speeds stay
+    pub fn clear(&mut self) {
+        self.slow_since.clear();
+        self.hazards.clear();
+        self.warned.clear();
+    }
+
+    /// learns the usual speed from a car at racing speed
+    pub fn sample(&mut self, update: &udp::RealtimeCarUpdate) {
+        if update.car_location != udp::CarLocation::Track || self.is_slow(update) {
+            return;
+        }
+        let speed = &mut self.profile[bin(update.spline_position)];
+        speed.samples += 1;
+        let weight = 1.0 / speed.samples.min(MAX_WEIGHT) as f32;
+        speed.kmh += (update.kmh as f32 - speed.kmh) * weight;
+    }
+
+    /// usual speed at a spline position, once enough cars went past
+    pub fn usual_kmh(&self, spline_position: f32) -> Option<f32> {
+        let speed = self.profile[bin(spline_position)];
+        (speed.samples >= MIN_SAMPLES).then_some(speed.kmh)
+    }
+
+    fn is_slow(&self, update: &udp::RealtimeCarUpdate) -> bool {
+        let kmh = update.kmh as f32;
+        kmh < STOPPED_KMH
+            || self
+                .usual_kmh(update.spline_position)
+                .is_some_and(|usual| kmh < usual * SLOW_FRACTION)
+    }
+
+    /// re-checks every car, returns the warnings for our driver
+    ///
+    /// Only green running counts: before the start, after the flag and
+    /// while the field is neutralized everybody is slow, so no new hazards
+    /// are raised then.
+    pub fn update(
+        &mut self,
+        now: f32,
+        cars: &HashMap<u16, Car>,
+        focused: Option<u16>,
+        green: bool,
+        record: Option<&TrackRecord>,
+    ) -> Vec<Alert> {
+        let mut hazards = vec![];
+        for car in cars.values() {
+            let index = car.car_info.car_index;
+            let slow = car.realtime.as_ref().filter(|update| {
+                update.car_location == udp::CarLocation::Track && green && self.is_slow(update)
+            });
+            let Some(update) = slow else {
+                self.slow_since.remove(&index);
+                continue;
+            };
+            let since = *self.slow_since.entry(index).or_insert(now);
+            if now - since >= CONFIRM_MS {
+                hazards.push(Hazard {
+                    car_index: index,
+                    race_number: car.car_info.race_number,
+                    spline_position: update.spline_position,
+                    since,
+                    kmh: update.kmh,
+                    stopped: (update.kmh as f32) < STOPPED_KMH,
+                });
+            }
+        }
+        for hazard in &hazards {
+            if !self.hazards.iter().any(|h| h.car_index == hazard.car_index) {
+                debug!("hazard: {}", hazard.describe(record));
+            }
+        }
+        self.hazards = hazards;
+        self.warned
+            .retain(|index| self.hazards.iter().any(|h| h.car_index == *index));
+
+        let Some(ours) = focused.and_then(|index| cars.get(&index)) else {
+            return vec![];
+        };
+        let (Some(update), Some(speed)) = (&ours.realtime, ours.speed()) else {
+            return vec![];
+        };
+        if update.car_location != udp::CarLocation::Track || speed <= 0.0 {
+            return vec![];
+        }
+        let mut alerts = vec![];
+        for hazard in &self.hazards {
+            if hazard.car_index == ours.car_info.car_index {
+                continue;
+            }
+            let ahead = spline_delta(update.spline_position, hazard.spline_position);
+            if ahead <= 0.0 {
+                // passed it, warn again on the next lap
+                self.warned.remove(&hazard.car_index);
+                continue;
+            }
+            let seconds = ahead / speed / 1000.0;
+            if seconds <= WARN_S && !self.warned.contains(&hazard.car_index) {
+                self.warned.insert(hazard.car_index);
+                alerts.push(Alert::warning(
+                    now,
+                    format!("yellow ahead: {}, {:.0}s", hazard.describe(record), seconds),
+                ));
+            }
+        }
+        alerts
+    }
+
+    /// hazards our driver has been warned about
+    pub fn warned(&self) -> impl Iterator<Item = &Hazard> {
+        self.hazards
+            .iter()
+            .filter(|hazard| self.warned.contains(&hazard.car_index))
+    }
+}
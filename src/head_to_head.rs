@@ -0,0 +1,95 @@
+//! Module for sector head to head comparisons
+//!
+//! Puts the sector times of two cars side by side lap for lap and sums up
+//! where one of them gains or loses time over the current stint.
+
+use crate::session::{Car, Lap};
+
+pub const SECTORS: usize = 3;
+/// share of laps a car has to be faster in a sector to count as stronger there
+const CONSISTENT_SHARE: f32 = 0.7;
+/// laps needed before a sector is called
+const MIN_LAPS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct LapPair {
+    pub number: u16,
+    pub a: [Option<u32>; SECTORS],
+    pub b: [Option<u32>; SECTORS],
+}
+
+impl LapPair {
+    /// time `b` lost to `a` in a sector in ms, negative when `b` was faster
+    pub fn delta(&self, sector: usize) -> Option<i64> {
+        Some(self.b[sector]? as i64 - self.a[sector]? as i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stronger {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// laps both cars drove cleanly, oldest first
+    pub laps: Vec<LapPair>,
+    /// summed `LapPair::delta` per sector over the current stint of `a`
+    pub stint_delta: [i64; SECTORS],
+    /// which car is faster in a sector on most laps
+    pub stronger: [Option<Stronger>; SECTORS],
+}
+
+fn sectors(lap: &Lap) -> [Option<u32>; SECTORS] {
+    let mut sectors = [None; SECTORS];
+    for (sector, split) in lap.info.lap_splits.iter().take(SECTORS).enumerate() {
+        sectors[sector] = Some(*split).filter(|split| *split > 0 && *split < i32::MAX as u32);
+    }
+    sectors
+}
+
+/// compares `b` against `a` on the laps both completed cleanly
+pub fn compare(a: &Car, b: &Car) -> Comparison {
+    let laps: Vec<LapPair> = a
+        .clean_laps()
+        .filter_map(|lap_a| {
+            let lap_b = b.clean_laps().find(|lap| lap.number == lap_a.number)?;
+            Some(LapPair {
+                number: lap_a.number,
+                a: sectors(lap_a),
+                b: sectors(lap_b),
+            })
+        })
+        .collect();
+
+    let stint_start = a.stops().last().map_or(0, |stop| stop.lap);
+    let mut stint_delta = [0; SECTORS];
+    let mut stronger = [None; SECTORS];
+    for sector in 0..SECTORS {
+        stint_delta[sector] = laps
+            .iter()
+            .filter(|pair| pair.number > stint_start)
+            .filter_map(|pair| pair.delta(sector))
+            .sum();
+
+        let deltas: Vec<i64> = laps.iter().filter_map(|pair| pair.delta(sector)).collect();
+        if deltas.len() < MIN_LAPS {
+            continue;
+        }
+        let b_faster = deltas.iter().filter(|delta| **delta < 0).count() as f32;
+        let a_faster = deltas.iter().filter(|delta| **delta > 0).count() as f32;
+        let total = deltas.len() as f32;
+        if b_faster / total >= CONSISTENT_SHARE {
+            stronger[sector] = Some(Stronger::B);
+        } else if a_faster / total >= CONSISTENT_SHARE {
+            stronger[sector] = Some(Stronger::A);
+        }
+    }
+
+    Comparison {
+        laps,
+        stint_delta,
+        stronger,
+    }
+}
@@ -0,0 +1,131 @@
+//! Module for the headless data logger
+//!
+//! Runs the UDP pipeline and the session model without any window, keeps
+//! the archive and results reports up to date and prints the standings and
+//! spotter cues to the terminal. `record` only writes the packets to a
+//! capture file.
+
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use log::{error, info};
+
+use crate::{
+    archive::Archive,
+    capture::Recorder,
+    classification,
+    pipeline::{Live, Pipeline},
+    report,
+    session::Session,
+    spotter::{Spotter, SpotterConfig},
+    utils,
+};
+
+/// how often the standings are printed
+const PRINT_INTERVAL: Duration = Duration::from_secs(5);
+/// how often the running session is written to the archive
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// the standings as a text table
+pub fn standings_table(session: &Session) -> String {
+    let mut out = format!(
+        "{:>3} {:>8} {:>4}  {:<24} {:>4} {:>9} {:>10} {:>10} {:>3}\n",
+        "P", "class", "#", "driver", "laps", "gap", "best", "last", "pit"
+    );
+    for entry in classification::classify(session) {
+        let car = entry.car;
+        let driver = car
+            .car_info
+            .drivers
+            .get(car.car_info.current_driver_index as usize)
+            .map_or(String::new(), |driver| {
+                format!("{} {}", driver.first_name, driver.last_name)
+            });
+        let best = car
+            .realtime
+            .as_ref()
+            .map(|update| update.best_session_lap.laptime_ms)
+            .filter(|laptime| *laptime > 0 && *laptime < i32::MAX as u32)
+            .map_or(String::from("-"), utils::ms_to_string);
+        let last = car.laps.last().map_or(String::from("-"), |lap| {
+            utils::ms_to_string(lap.info.laptime_ms)
+        });
+        out.push_str(&format!(
+            "{:>3} {:>8} {:>4}  {:<24} {:>4} {:>9} {:>10} {:>10} {:>3}\n",
+            car.position(),
+            format!("{} P{}", entry.class, entry.class_position),
+            car.car_info.race_number,
+            driver.chars().take(24).collect::<String>(),
+            car.lap_count(),
+            entry.gap_to_leader.to_string(),
+            best,
+            last,
+            car.stops().count()
+        ));
+    }
+    out
+}
+
+/// runs until the process is stopped
+pub fn run(addr: SocketAddr, recorder: Option<Recorder>) -> Result<(), String> {
+    info!("starting headless logger");
+    let mut pipeline = Pipeline::connect(addr, recorder)?;
+    let archive = Archive::open()
+        .inspect_err(|e| error!("could not open session archive: {}", e))
+        .ok();
+    let spotter = Spotter::new(SpotterConfig::load());
+    let mut live = Live::new(archive, Some(report::default_dir()), spotter);
+    let mut last_print = Instant::now();
+    let mut last_archive_save = Instant::now();
+
+    loop {
+        match pipeline.next() {
+            Ok(messages) => {
+                for message in messages {
+                    let applied = live.apply(message);
+                    if let Some(path) = applied.report {
+                        println!("results report written to {}", path.display());
+                    }
+                    for cue in applied.cues {
+                        println!("spotter: {}", cue.text);
+                    }
+                }
+            }
+            Err(e) => error!("could not read packet: {}", e),
+        }
+
+        let now = Instant::now();
+        if now.duration_since(last_print) >= PRINT_INTERVAL {
+            let session = &live.session;
+            println!(
+                "{} {} left",
+                session
+                    .track
+                    .as_ref()
+                    .map_or("waiting for track data", |track| track.track_name.as_str()),
+                utils::ms_to_string(session.remaining_time() as u32)
+            );
+            print!("{}", standings_table(session));
+            last_print = now;
+        }
+        if now.duration_since(last_archive_save) >= ARCHIVE_INTERVAL {
+            live.archive_session();
+            live.session.save_track();
+            last_archive_save = now;
+        }
+    }
+}
+
+/// writes every packet to `recorder` until the process is stopped, without
+/// the session model, archive or reports
+pub fn record(addr: SocketAddr, recorder: Recorder) -> Result<(), String> {
+    info!("starting recorder");
+    let mut pipeline = Pipeline::connect(addr, Some(recorder))?;
+    loop {
+        if let Err(e) = pipeline.next() {
+            error!("could not read packet: {}", e);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Module for inspecting capture files
+//!
+//! Prints the decoded messages of a capture as text or JSON lines,
+//! optionally limited to some message types and cars, with the byte offset
+//! of every field. Packets that fail to decode are shown as a hexdump
+//! around the point where decoding stopped.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{capture, udp};
+
+/// bytes shown before and after the failing offset
+const HEXDUMP_CONTEXT: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum MessageType {
+    RegistrationResult,
+    RealtimeUpdate,
+    RealtimeCarUpdate,
+    EntryList,
+    TrackData,
+    EntryListCar,
+    BroadcastingEvent,
+}
+
+impl MessageType {
+    fn matches(&self, inbound: &udp::Inbound) -> bool {
+        self.to_possible_value()
+            .is_some_and(|value| value.get_name() == inbound.name())
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// capture file to read
+    pub file: PathBuf,
+    /// only show these message types
+    #[arg(long = "type", value_enum)]
+    pub types: Vec<MessageType>,
+    /// only show messages about these car indexes
+    #[arg(long = "car")]
+    pub cars: Vec<u16>,
+    /// print one JSON object per line
+    #[arg(long)]
+    pub json: bool,
+    /// also print the offset, size and value of every field
+    #[arg(long)]
+    pub fields: bool,
+    /// print message counts and update rates instead of the messages
+    #[arg(long)]
+    pub stats: bool,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    at_ms: u32,
+    #[serde(rename = "type")]
+    message_type: &'static str,
+    message: &'a udp::Inbound,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<&'a [udp::Field]>,
+}
+
+#[derive(Default)]
+struct Stats {
+    packets: usize,
+    failures: usize,
+    first_ms: Option<u32>,
+    last_ms: u32,
+    types: BTreeMap<&'static str, usize>,
+    /// realtime car updates per car index
+    cars: BTreeMap<u16, usize>,
+}
+
+impl Stats {
+    fn add(&mut self, at_ms: u32, inbound: Option<&udp::Inbound>) {
+        self.packets += 1;
+        self.first_ms.get_or_insert(at_ms);
+        self.last_ms = at_ms;
+        let Some(inbound) = inbound else {
+            self.failures += 1;
+            return;
+        };
+        *self.types.entry(inbound.name()).or_default() += 1;
+        if let udp::Inbound::RealtimeCarUpdate(update) = inbound {
+            *self.cars.entry(update.car_index).or_default() += 1;
+        }
+    }
+
+    /// time between the first and the last packet
+    fn seconds(&self) -> f32 {
+        self.last_ms
+            .saturating_sub(self.first_ms.unwrap_or_default()) as f32
+            / 1000.0
+    }
+
+    fn rate(&self, count: usize) -> String {
+        let seconds = self.seconds();
+        if seconds > 0.0 {
+            format!("{:.1}", count as f32 / seconds)
+        } else {
+            String::from("-")
+        }
+    }
+
+    fn print(&self) {
+        println!(
+            "{} packets over {:.1} s, {} could not be decoded",
+            self.packets,
+            self.seconds(),
+            self.failures
+        );
+        println!();
+        println!("{:<22} {:>8} {:>8}", "type", "count", "per s");
+        for (name, count) in &self.types {
+            println!("{:<22} {:>8} {:>8}", name, count, self.rate(*count));
+        }
+        if !self.cars.is_empty() {
+            println!();
+            println!("{:>4} {:>8} {:>8}", "car", "updates", "per s");
+            for (car, count) in &self.cars {
+                println!("{:>4} {:>8} {:>8}", car, count, self.rate(*count));
+            }
+        }
+    }
+}
+
+/// hex and ascii rows around `at`, the byte at `at` is marked with `>`
+fn hexdump(bytes: &[u8], at: usize) -> String {
+    let start = at.saturating_sub(HEXDUMP_CONTEXT) / 16 * 16;
+    let end = (at + HEXDUMP_CONTEXT).min(bytes.len());
+    let mut out = String::new();
+    for row in (start..end.max(start + 1)).step_by(16) {
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        for offset in row..row + 16 {
+            let marker = if offset == at { '>' } else { ' ' };
+            match bytes.get(offset) {
+                Some(byte) => {
+                    hex.push_str(&format!("{}{:02x}", marker, byte));
+                    ascii.push(if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    });
+                }
+                None => hex.push_str(&format!("{}  ", marker)),
+            }
+        }
+        out.push_str(&format!("{:>6} {}  {}\n", row, hex, ascii));
+    }
+    if at >= bytes.len() {
+        out.push_str(&format!("{:>6} > end of packet\n", bytes.len()));
+    }
+    out
+}
+
+fn print_fields(fields: &[udp::Field]) {
+    for field in fields {
+        println!(
+            "{:>9}   {:>5} {:>3} {:<6} {:<36} {}",
+            "", field.offset, field.len, field.kind, field.name, field.value
+        );
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let packets = capture::read(&args.file)?;
+    let mut reader = udp::UdpReader::new();
+    reader.trace_fields(args.fields);
+    let mut stats = Stats::default();
+    for packet in &packets {
+        let at_ms = packet.at_ms;
+        let inbound = match reader
+            .load(&packet.bytes)
+            .and_then(|_| udp::parse(&mut reader))
+        {
+            Ok(inbound) => inbound,
+            Err(e) => {
+                stats.add(at_ms, None);
+                if !args.stats {
+                    eprintln!("{:>9} could not decode packet: {}", at_ms, e);
+                    eprintln!(
+                        "{:>9} decoding stopped at offset {} of {}",
+                        "",
+                        reader.pointer(),
+                        packet.bytes.len()
+                    );
+                    eprint!("{}", hexdump(&packet.bytes, reader.pointer()));
+                }
+                continue;
+            }
+        };
+        if !args.types.is_empty() && !args.types.iter().any(|t| t.matches(&inbound)) {
+            continue;
+        }
+        if !args.cars.is_empty()
+            && !inbound
+                .car_index()
+                .is_some_and(|index| args.cars.contains(&index))
+        {
+            continue;
+        }
+        stats.add(at_ms, Some(&inbound));
+        if args.stats {
+            continue;
+        }
+        if args.json {
+            let line = Line {
+                at_ms,
+                message_type: inbound.name(),
+                message: &inbound,
+                fields: args.fields.then(|| reader.fields()),
+            };
+            println!(
+                "{}",
+                serde_json::to_string(&line).map_err(|e| e.to_string())?
+            );
+        } else {
+            println!("{:>9} {} {:?}", at_ms, inbound.name(), inbound);
+            if args.fields {
+                print_fields(reader.fields());
+            }
+        }
+    }
+    if args.stats {
+        stats.print();
+    }
+    Ok(())
+}
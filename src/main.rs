@@ -1,327 +1,547 @@
 #![allow(dead_code)]
 use std::{
-    cell::{Ref, RefCell},
-    collections::{HashMap, VecDeque},
-    mem::drop,
+    collections::{BTreeSet, HashMap},
     net::SocketAddr,
-    sync::Arc,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
 use iced::{
-    futures::{SinkExt, Stream},
+    futures::{channel::mpsc, SinkExt, Stream, StreamExt},
     stream,
-    widget::{column, container, row, text, Column, Row},
+    widget::{button, column, container, row, text, Column},
     window::{self, Settings},
     Element,
     Length::Fill,
     Result, Subscription, Task,
 };
 
-use log::{debug, error, info, trace};
+use clap::{Parser, Subcommand};
+use log::{error, info, trace};
 
+mod alerts;
+mod archive;
+mod capture;
+mod car_models;
+mod classification;
+mod export;
+#[cfg(test)]
+mod fixture;
+mod fuel;
+mod hazards;
+mod head_to_head;
+mod headless;
+mod inspect;
 mod mm;
+mod neutral;
+mod pace;
+mod pipeline;
+mod pit;
+mod positions;
+mod projection;
+mod race_control;
+mod replay;
+mod report;
+mod session;
+mod spotter;
+mod track;
+mod traffic;
+mod tyres;
 mod udp;
 mod utils;
+mod views;
+mod weather;
 
-#[derive(Debug)]
-struct Car {
-    car_info: udp::CarInfo,
-    laps: Vec<udp::LapInfo>,
-    update_ready: bool,
-    lap_count: u16,
-    position: u16,
-    prev: Option<u16>,
-    next: Option<u16>,
+/// how often learned track geometry is written to disk
+const TRACK_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// how often the running session is written to the archive
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum View {
+    Main,
+    TrackMap,
+    Positions,
+    RaceControl,
+    Charts,
+    Pace,
+    HeadToHead,
+    Sessions,
 }
 
 struct Backmarker {
-    /// Maps car index to `Car` struct
-    cars: HashMap<u16, RefCell<Car>>,
-    /// Car index of the leader
-    leader: Option<u16>,
-    last: Option<u16>,
-    update_queue: Vec<u16>,
+    source: Source,
+    /// the running session with its archive and reports
+    live: pipeline::Live,
+    /// Maps open windows to the view they show
+    windows: HashMap<window::Id, View>,
+    last_track_save: Instant,
+    standings_order: classification::Order,
+    class_filter: Option<car_models::CarClass>,
+    position_filter: views::positions::Filter,
+    chart_filter: Option<car_models::CarClass>,
+    /// cars compared in the pace window
+    pace_cars: BTreeSet<u16>,
+    /// cars compared sector by sector
+    head_to_head: (Option<u16>, Option<u16>),
+    voice: spotter::Voice,
+    last_archive_save: Instant,
+    /// sessions listed in the session browser
+    archived_sessions: Vec<archive::Summary>,
+    /// past session shown read-only instead of the live one
+    archived: Option<(i64, session::Session)>,
+    /// outcome of the last export, shown in the main window
+    export_status: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Message {
     Tick(Instant),
+    RealtimeUpdate(udp::RealtimeUpdate),
     RealTimeCarUpdate(udp::RealtimeCarUpdate),
     EntryList(udp::EntryList),
     CarInfo(udp::CarInfo),
+    TrackData(udp::TrackData),
     BroadcastingEvent(udp::BroadcastingEvent),
+    MandatoryStops(u16),
+    TyreSample(tyres::TyreSample),
+    /// fuel in the tank in liters
+    Fuel(f32),
+    Tyres(tyres::TyreAction),
+    StandingsOrder(classification::Order),
+    ClassFilter(Option<car_models::CarClass>),
+    ToggleSpotter,
+    PositionFilter(views::positions::Filter),
+    ChartFilter(Option<car_models::CarClass>),
+    TogglePaceCar(u16),
+    HeadToHead(views::head_to_head::Side, u16),
+    RefreshArchive,
+    OpenArchived(i64),
+    BackToLive,
+    /// writes the shown session as CSV and JSON
+    Export,
+    /// directory the export was written to
+    Exported(std::result::Result<PathBuf, String>),
+    OpenWindow(View),
+    WindowClosed(window::Id),
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Live timing and strategy for ACC")]
+struct Cli {
+    /// defaults to `live`
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// live timing windows fed from ACC
+    Live {
+        /// also record every packet to this capture file
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// log standings to the terminal without any window
+    Headless {
+        /// also record every packet to this capture file
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// record every packet to a capture file without any window
+    Record { file: PathBuf },
+    /// play a capture file back in the live timing windows
+    Replay {
+        file: PathBuf,
+        /// playback speed, 2 plays twice as fast
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+    },
+    /// print the decoded messages of a capture file
+    Inspect(inspect::Args),
+    /// write CSV, JSON and the results report of a capture file
+    Export {
+        /// capture file to export
+        #[arg(required_unless_present = "archived")]
+        file: Option<PathBuf>,
+        /// export a session from the archive instead
+        #[arg(long, conflicts_with = "file")]
+        archived: Option<i64>,
+        /// directory to write to, a fresh one in the data directory otherwise
+        #[arg(long, short)]
+        out: Option<PathBuf>,
+    },
+}
+
+/// where the window gets its packets from
+#[derive(Debug, Clone)]
+enum Source {
+    Live { record: Option<PathBuf> },
+    Replay { file: PathBuf, speed: f32 },
 }
 
 fn main() -> Result {
     env_logger::init();
+    let cli = Cli::parse();
+    let source = match cli.command.unwrap_or(Command::Live { record: None }) {
+        Command::Live { record } => Source::Live { record },
+        Command::Replay { file, speed } => Source::Replay { file, speed },
+        Command::Headless { record } => exit_on_error(run_headless(record)),
+        Command::Record { file } => exit_on_error(run_record(&file)),
+        Command::Inspect(args) => exit_on_error(inspect::run(&args)),
+        Command::Export {
+            file,
+            archived,
+            out,
+        } => exit_on_error(run_export(file, archived, out)),
+    };
     info!("backmarker started");
     iced::daemon("backmarker", Backmarker::update, Backmarker::view)
         .subscription(Backmarker::subscription)
-        .run_with(move || Backmarker::new())
+        .run_with(move || Backmarker::new(source))
+}
+
+/// ends a command that does not open a window
+fn exit_on_error(result: std::result::Result<(), String>) -> ! {
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_export(
+    file: Option<PathBuf>,
+    archived: Option<i64>,
+    out: Option<PathBuf>,
+) -> std::result::Result<(), String> {
+    let session = match (file, archived) {
+        (_, Some(id)) => archive::Archive::open()?.load(id)?,
+        (Some(file), None) => replay::session(&file)?,
+        (None, None) => return Err(String::from("nothing to export")),
+    };
+    let dir = out.unwrap_or_else(|| export::default_dir(&session));
+    export::write(&session, &dir)?;
+    report::write_to(&session, &dir.join("report"))?;
+    println!("exported to {}", dir.display());
+    Ok(())
+}
+
+fn run_headless(record: Option<PathBuf>) -> std::result::Result<(), String> {
+    let addr: SocketAddr = pipeline::ACC_ADDRESS
+        .parse()
+        .map_err(|_| String::from("unable to parse address"))?;
+    let recorder = record
+        .map(|path| capture::Recorder::create(&path))
+        .transpose()?;
+    if let Some(recorder) = &recorder {
+        recorder.flush_on_interrupt()?;
+    }
+    headless::run(addr, recorder)
+}
+
+fn run_record(file: &Path) -> std::result::Result<(), String> {
+    let addr: SocketAddr = pipeline::ACC_ADDRESS
+        .parse()
+        .map_err(|_| String::from("unable to parse address"))?;
+    let recorder = capture::Recorder::create(file)?;
+    recorder.flush_on_interrupt()?;
+    headless::record(addr, recorder)
 }
 
 impl Backmarker {
-    fn new() -> (Backmarker, Task<Message>) {
+    fn new(source: Source) -> (Backmarker, Task<Message>) {
         info!("starting ui");
+        let (main_window_id, open_main_window) = window::open(Settings::default());
+
+        // replayed sessions are in the capture already
+        let archive = match source {
+            Source::Live { .. } => archive::Archive::open()
+                .inspect_err(|e| error!("could not open session archive: {}", e))
+                .ok(),
+            Source::Replay { .. } => None,
+        };
         let bm = Backmarker {
-            cars: HashMap::new(),
-            leader: None,
-            last: None,
-            update_queue: vec![],
+            source,
+            live: pipeline::Live::new(
+                archive,
+                Some(report::default_dir()),
+                spotter::Spotter::new(spotter::SpotterConfig::load()),
+            ),
+            windows: HashMap::from([(main_window_id, View::Main)]),
+            last_track_save: Instant::now(),
+            standings_order: classification::Order::Overall,
+            class_filter: None,
+            position_filter: views::positions::Filter::default(),
+            chart_filter: None,
+            pace_cars: BTreeSet::new(),
+            head_to_head: (None, None),
+            voice: spotter::Voice::new(),
+            last_archive_save: Instant::now(),
+            archived_sessions: vec![],
+            archived: None,
+            export_status: None,
         };
 
-        let (_main_window_id, open_main_window) = window::open(Settings::default());
-
         (bm, open_main_window.then(|_| Task::none()))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::Tick(_now) => Task::none(),
-            Message::RealTimeCarUpdate(realtime_update) => {
-                trace!("realtime update message");
-                if self.cars.contains_key(&realtime_update.car_index)
-                    && self.update_queue.contains(&realtime_update.car_index)
-                {
-                    let queue_index = self
-                        .update_queue
-                        .binary_search(&realtime_update.car_index)
-                        .unwrap();
-                    self.update_queue.remove(queue_index);
-                    {
-                        let mut car = self
-                            .cars
-                            .get(&realtime_update.car_index)
-                            .unwrap()
-                            .borrow_mut();
-
-                        car.laps.push(realtime_update.last_lap);
-                        car.update_ready = false;
-                        car.lap_count = realtime_update.laps;
-
-                        if car.position != realtime_update.position {
-                            if realtime_update.car_index == car.car_info.car_index {
-                                car.position = realtime_update.position
-                            } else {
-                                self.cars
-                                    .get(&realtime_update.car_index)
-                                    .unwrap()
-                                    .borrow_mut()
-                                    .position = realtime_update.position;
-                            }
-                        }
-                    }
-
-                    let car = self.cars.get(&realtime_update.car_index).unwrap().borrow();
-
-                    if car.position != realtime_update.position {
-                        // remove step
-                        if car.prev.is_some() {
-                            let prev = car.prev.unwrap();
-                            self.cars.get(&prev).unwrap().borrow_mut().next = car.next;
-                            if car.next.is_some() {
-                                // prev <- next
-                                self.cars.get(&car.next.unwrap()).unwrap().borrow_mut().prev =
-                                    car.prev;
-                            }
-                        }
-
-                        // add step
-                        let mut inserting_car = self
-                            .cars
-                            .get(&self.find_position_index_or(realtime_update.position))
-                            .unwrap()
-                            .borrow_mut();
-                        if inserting_car.prev.is_some() {
-                            self.cars
-                                .get(&inserting_car.prev.unwrap())
-                                .unwrap()
-                                .borrow_mut()
-                                .next = Some(car.car_info.car_index);
-
-                            inserting_car.prev = Some(car.car_info.car_index);
-                        } else {
-                            self.leader = Some(car.car_info.car_index);
-                        }
-                    }
+            Message::Tick(now) => {
+                if now.duration_since(self.last_track_save) >= TRACK_SAVE_INTERVAL {
+                    self.live.session.save_track();
+                    self.last_track_save = now;
+                }
+                if now.duration_since(self.last_archive_save) >= ARCHIVE_INTERVAL {
+                    self.live.archive_session();
+                    self.last_archive_save = now;
                 }
-                Task::none()
             }
-            Message::CarInfo(car_info) => {
-                trace!("car info message");
-                let index = car_info.car_index;
-                if !self.cars.contains_key(&index) {
-                    self.cars.insert(
-                        car_info.car_index.into(),
-                        RefCell::new(Car {
-                            car_info,
-                            laps: vec![],
-                            update_ready: false,
-                            lap_count: 0,
-                            position: 0,
-                            prev: self.last,
-                            next: None,
-                        }),
-                    );
-
-                    if self.leader.is_none() {
-                        self.leader = Some(index);
-                    } else {
-                        let mut curr = self.cars.get(&self.last.unwrap()).unwrap().borrow_mut();
-                        curr.next = Some(index);
-                        curr.prev = self.last;
+            Message::RealtimeUpdate(_)
+            | Message::RealTimeCarUpdate(_)
+            | Message::CarInfo(_)
+            | Message::EntryList(_)
+            | Message::TrackData(_)
+            | Message::BroadcastingEvent(_)
+            | Message::TyreSample(_)
+            | Message::Fuel(_) => {
+                trace!("session message");
+                for cue in self.live.apply(message).cues {
+                    self.voice.say(&cue);
+                }
+            }
+            Message::MandatoryStops(stops) => {
+                if self.archived.is_some() {
+                    return Task::none();
+                }
+                self.live.session.mandatory_stops = stops;
+                self.live.session.update_projection();
+            }
+            Message::Tyres(action) => {
+                if self.archived.is_some() {
+                    return Task::none();
+                }
+                self.live.session.tyres.perform(action);
+            }
+            Message::StandingsOrder(order) => {
+                self.standings_order = order;
+            }
+            Message::ClassFilter(filter) => {
+                self.class_filter = filter;
+            }
+            Message::PositionFilter(filter) => {
+                self.position_filter = filter;
+            }
+            Message::ChartFilter(filter) => {
+                self.chart_filter = filter;
+            }
+            Message::TogglePaceCar(index) => {
+                if !self.pace_cars.remove(&index) {
+                    self.pace_cars.insert(index);
+                }
+            }
+            Message::HeadToHead(side, index) => match side {
+                views::head_to_head::Side::A => self.head_to_head.0 = Some(index),
+                views::head_to_head::Side::B => self.head_to_head.1 = Some(index),
+            },
+            Message::ToggleSpotter => {
+                self.live.spotter.config.enabled = !self.live.spotter.config.enabled;
+                if let Err(e) = self.live.spotter.config.save() {
+                    error!("could not save spotter config: {}", e);
+                }
+            }
+            Message::RefreshArchive => self.refresh_archive(),
+            Message::OpenArchived(id) => {
+                let Some(archive) = self.live.archive() else {
+                    return Task::none();
+                };
+                match archive.load(id) {
+                    Ok(session) => {
+                        info!("showing archived session {}", id);
+                        self.archived = Some((id, session));
                     }
-                    self.last = Some(index);
+                    Err(e) => error!("could not load archived session {}: {}", id, e),
                 }
-                Task::none()
             }
-            Message::BroadcastingEvent(broadcast) => {
-                trace!("broadcast event message");
-                match broadcast.event_type {
-                    udp::BroadcastingEventType::LapCompleted => {
-                        self.update_queue.push(broadcast.car_id as u16);
+            Message::BackToLive => {
+                self.archived = None;
+            }
+            Message::Export => {
+                let session = self.shown();
+                let (tables, dir) = (export::collect(session), export::default_dir(session));
+                self.export_status = Some(String::from("exporting…"));
+                return Task::perform(
+                    async move { export::write_export(&tables, &dir).map(|_| dir) },
+                    Message::Exported,
+                );
+            }
+            Message::Exported(result) => {
+                self.export_status = Some(match result {
+                    Ok(dir) => format!("exported to {}", dir.display()),
+                    Err(e) => {
+                        error!("could not export session: {}", e);
+                        format!("export failed: {}", e)
                     }
-                    _ => {}
+                });
+            }
+            Message::OpenWindow(view) => {
+                if view == View::Sessions {
+                    self.refresh_archive();
+                }
+                if let Some(id) = self.window_of(view) {
+                    return window::gain_focus(id);
+                }
+                let (id, open_window) = window::open(Settings::default());
+                self.windows.insert(id, view);
+                return open_window.then(|_| Task::none());
+            }
+            Message::WindowClosed(id) => {
+                if self.windows.remove(&id) == Some(View::Main) {
+                    info!("main window closed, exiting");
+                    self.live.session.save_track();
+                    self.live.archive_session();
+                    self.live.close_archive();
+                    return iced::exit();
                 }
-                Task::none()
             }
-            Message::EntryList(entry_list) => Task::none(),
-            _ => Task::none(),
         }
+        Task::none()
     }
 
-    fn view(&self, _id: window::Id) -> Element<Message> {
-        trace!("rendering!");
-        debug! {"cars: {:#?}", self.cars};
-        let mut col_vec: Vec<Element<'_, _, _, _>> = vec![];
+    fn refresh_archive(&mut self) {
+        let Some(archive) = self.live.archive() else {
+            return;
+        };
+        match archive.sessions() {
+            Ok(sessions) => self.archived_sessions = sessions,
+            Err(e) => error!("could not list archived sessions: {}", e),
+        }
+    }
 
-        if self.leader.is_some() {
-            let mut car = self.cars.get(&self.leader.unwrap());
-            while car.is_some() {
-                // temporary
-                let laptime = if car.unwrap().borrow().laps.last().is_none() {
-                    0
-                } else {
-                    car.unwrap().borrow().laps.last().unwrap().laptime_ms
-                };
-                col_vec.push(
-                    container(
-                        row![
-                            text(car.unwrap().borrow().position),
-                            text(car.unwrap().borrow().car_info.race_number),
-                            text(utils::ms_to_string(laptime))
-                        ]
-                        .spacing(4),
-                    )
-                    .into(),
-                );
-                if car.unwrap().borrow().next.is_none() {
-                    break;
-                }
-                car = self.cars.get(&car.unwrap().borrow().next.unwrap());
+    /// the session the views render, an archived one while it is open
+    fn shown(&self) -> &session::Session {
+        self.archived
+            .as_ref()
+            .map_or(&self.live.session, |(_, session)| session)
+    }
+
+    fn window_of(&self, view: View) -> Option<window::Id> {
+        self.windows
+            .iter()
+            .find(|(_, open)| **open == view)
+            .map(|(id, _)| *id)
+    }
+
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        trace!("rendering!");
+        match self.windows.get(&id) {
+            // archived cars have no position on track
+            Some(View::TrackMap) if self.archived.is_some() => {
+                text("track map is not available for archived sessions").into()
             }
+            Some(View::TrackMap) => views::track_map::view(self.shown()),
+            Some(View::Positions) => views::positions::view(self.shown(), self.position_filter),
+            Some(View::RaceControl) => views::race_control::view(self.shown()),
+            Some(View::Charts) => views::charts::view(self.shown(), self.chart_filter),
+            Some(View::Pace) => views::pace::view(self.shown(), &self.pace_cars),
+            Some(View::HeadToHead) => {
+                views::head_to_head::view(self.shown(), self.head_to_head.0, self.head_to_head.1)
+            }
+            Some(View::Sessions) => views::sessions::view(
+                &self.archived_sessions,
+                self.archived.as_ref().map(|(id, _)| *id),
+            ),
+            _ => self.main_view(),
         }
-        container(Column::from_vec(col_vec))
-            .center_x(Fill)
-            .center_y(Fill)
-            .into()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        let tick = iced::time::every(Duration::from_millis(100)).map(Message::Tick);
-        let udp_sub = Subscription::run(udp_worker);
-        Subscription::batch(vec![tick, udp_sub])
+    fn main_view(&self) -> Element<'_, Message> {
+        let banner = self.archived.as_ref().map(|_| {
+            row![
+                text("viewing an archived session, read only"),
+                button("back to live").on_press(Message::BackToLive)
+            ]
+            .spacing(10)
+        });
+        container(
+            column![
+                Column::new().push_maybe(banner),
+                row![
+                    button("track map").on_press_maybe(
+                        self.archived
+                            .is_none()
+                            .then_some(Message::OpenWindow(View::TrackMap))
+                    ),
+                    button("position log").on_press(Message::OpenWindow(View::Positions)),
+                    button("race control").on_press(Message::OpenWindow(View::RaceControl)),
+                    button("charts").on_press(Message::OpenWindow(View::Charts)),
+                    button("pace").on_press(Message::OpenWindow(View::Pace)),
+                    button("head to head").on_press(Message::OpenWindow(View::HeadToHead)),
+                    button("sessions").on_press(Message::OpenWindow(View::Sessions)),
+                    button("export").on_press(Message::Export),
+                    button(if self.live.spotter.config.enabled {
+                        "spotter on"
+                    } else {
+                        "spotter off"
+                    })
+                    .on_press(Message::ToggleSpotter)
+                ]
+                .spacing(10),
+                Column::new().push_maybe(self.export_status.as_deref().map(text)),
+                row![
+                    views::standings::view(
+                        self.shown(),
+                        self.standings_order,
+                        self.class_filter,
+                        self.archived.is_some()
+                    ),
+                    match self.archived {
+                        Some(_) => Element::from(text("no relative board for archived sessions")),
+                        None => views::relative::view(&self.live.session),
+                    },
+                    views::tyres::view(self.shown(), self.archived.is_some())
+                ]
+                .spacing(20),
+                row![
+                    views::weather::view(self.shown()),
+                    views::alerts::view(self.shown())
+                ]
+                .spacing(20)
+            ]
+            .spacing(20),
+        )
+        .center_x(Fill)
+        .center_y(Fill)
+        .into()
     }
 
-    /// finds the car index at `position` on track or the last car
-    fn find_position_index_or(&self, position: u16) -> u16 {
-        let mut curr = self.cars.get(&self.leader.unwrap()).unwrap().borrow();
-        loop {
-            if curr.position == position {
-                return curr.car_info.car_index;
+    fn subscription(&self) -> Subscription<Message> {
+        let tick = iced::time::every(Duration::from_millis(100)).map(Message::Tick);
+        let udp_sub = match &self.source {
+            Source::Live { record } => {
+                Subscription::run_with_id("live", udp_worker(record.clone()))
             }
-            if curr.next.is_some() {
-                curr = self.cars.get(&curr.next.unwrap()).unwrap().borrow();
-            } else {
-                return curr.car_info.car_index;
+            Source::Replay { file, speed } => {
+                Subscription::run_with_id(("replay", file.clone()), replay::worker(file, *speed))
             }
-        }
+        };
+        let closed = window::close_events().map(Message::WindowClosed);
+        Subscription::batch(vec![tick, udp_sub, closed])
     }
 }
 
-fn udp_worker() -> impl Stream<Item = Message> {
+fn udp_worker(record: Option<PathBuf>) -> impl Stream<Item = Message> {
     stream::channel(100, |mut output| async move {
-        let addr: SocketAddr = "127.0.0.1:9000".parse().expect("unable to parse address");
-        let mut reader = udp::UdpReader::new();
-
-        let _recv_bytes = udp::connect(&reader.socket, addr).expect("cannot connect to ACC");
-        //setup memory mapping
-        //let memory_map = mm::MMReader::new();
-
-        loop {
-            // grab UDP data
-            reader.listen().unwrap(); // could be droping packets here
-            match udp::InboundMessageType::try_from(reader.read_u8().unwrap()).unwrap() {
-                udp::InboundMessageType::RegistrationResult => {
-                    let registration = udp::parse_registration_result(&mut reader).unwrap();
-                    info!("connected to acc!");
-                    trace!("{:#?}", registration);
-                    udp::request_entry_list(&reader.socket, registration.connection_id)
-                        .expect("could not send entrylist request");
-                    udp::request_track_data(&reader.socket, registration.connection_id)
-                        .expect("could not send trackdata request");
-                }
-                udp::InboundMessageType::RealtimeUpdate => {
-                    /*
-                    println!("realtime update");
-                    let realtime_update = parse_realtime_update(&mut reader).unwrap();
-                    println!("{:#?}", realtime_update);
-                    */
-                }
-                udp::InboundMessageType::RealtimeCarUpdate => {
-                    let realtime_update = udp::parse_realtime_car_update(&mut reader).unwrap();
-                    trace!("got RealtimeCarUpdate!");
-                    output
-                        .send(Message::RealTimeCarUpdate(realtime_update))
-                        .await
-                        .expect("could not send message");
-                }
-                udp::InboundMessageType::EntryList => {
-                    let entries = udp::parse_entry_list(&mut reader).unwrap();
-                    trace!("got entry list!");
-                    output
-                        .send(Message::EntryList(entries))
-                        .await
-                        .expect("could not send message");
-                }
-                udp::InboundMessageType::EntryListCar => {
-                    let car_info = udp::parse_entry_list_car(&mut reader).unwrap();
-                    trace!("got car info!");
-                    output
-                        .send(Message::CarInfo(car_info))
-                        .await
-                        .expect("could not send message");
-                }
-                udp::InboundMessageType::TrackData => {
-                    /*
-                    println!("track data");
-                    let track_data = parse_track_data(&mut reader).unwrap();
-                    println!("{:#?}", track_data);
-                    */
-                }
-                udp::InboundMessageType::BroadcastingEvent => {
-                    let broadcast = udp::parse_broadcasting_event(&mut reader).unwrap();
-                    trace!("got broadcasting event!");
-                    output
-                        .send(Message::BroadcastingEvent(broadcast))
-                        .await
-                        .expect("could not send message");
-                }
-            }
-            // grab shared memory data
-            // CHECK FOR NEW PACKETS FIRST??
-            //println!("struct: {:#?}", memory_map.get_physics().packet_id);
+        let addr: SocketAddr = pipeline::ACC_ADDRESS
+            .parse()
+            .expect("unable to parse address");
+        let (sender, mut receiver) = mpsc::unbounded();
+        pipeline::spawn(addr, record, sender);
+        while let Some(message) = receiver.next().await {
+            output.send(message).await.expect("could not send message");
         }
     })
 }
@@ -132,7 +132,7 @@ impl MMReader {
                 FILE_MAP_READ,
                 0,
                 0,
-                mem::size_of::<Physics>().try_into().unwrap(),
+                mem::size_of::<Physics>(),
             )
             .Value;
 
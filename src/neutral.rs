@@ -0,0 +1,163 @@
+//! Module for neutralization tracking
+//!
+//! Detects safety car and full course yellow periods from the session
+//! phase, broadcasting events and a field wide drop in speed, so laps
+//! driven under them can be kept out of pace averages.
+
+use log::debug;
+
+use crate::{alerts::Alert, udp};
+
+/// field speed below this fraction of the green flag speed counts as neutralized
+const SLOW_FRACTION: f32 = 0.6;
+/// field speed above this fraction of the green flag speed counts as racing
+const GREEN_FRACTION: f32 = 0.85;
+/// how long the field has to stay slow or fast before the state flips in ms
+const CONFIRM_MS: f32 = 3_000.0;
+/// weight of a new sample in the green flag speed average
+const BASELINE_WEIGHT: f32 = 0.02;
+/// fewest cars on track for the field speed to mean anything
+const MIN_CARS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cause {
+    /// the session is not in its racing phase, formation lap and such
+    SessionPhase,
+    /// the whole field slowed down, safety car or full course yellow
+    SpeedDrop,
+}
+
+#[derive(Debug, Clone)]
+pub struct Neutralization {
+    pub start: f32,
+    pub end: Option<f32>,
+    pub cause: Cause,
+}
+
+impl Neutralization {
+    /// true when the period overlaps the session time range `from..to`
+    pub fn overlaps(&self, from: f32, to: f32) -> bool {
+        self.start <= to && self.end.is_none_or(|end| end >= from)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NeutralTracker {
+    pub periods: Vec<Neutralization>,
+    /// median on track speed under green flag
+    baseline_kmh: Option<f32>,
+    /// last median on track speed
+    field_kmh: Option<f32>,
+    slow_since: Option<f32>,
+    fast_since: Option<f32>,
+}
+
+impl NeutralTracker {
+    pub fn current(&self) -> Option<&Neutralization> {
+        self.periods.last().filter(|period| period.end.is_none())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current().is_some()
+    }
+
+    /// true when any period overlaps the session time range `from..to`
+    pub fn overlaps(&self, from: f32, to: f32) -> bool {
+        self.periods.iter().any(|period| period.overlaps(from, to))
+    }
+
+    /// how many times slower than green flag pace the field is running
+    pub fn slowdown(&self) -> f32 {
+        match (self.current(), self.baseline_kmh, self.field_kmh) {
+            (Some(_), Some(baseline), Some(field)) if field > 1.0 => (baseline / field).max(1.0),
+            _ => 1.0,
+        }
+    }
+
+    fn start(&mut self, start: f32, cause: Cause) -> Option<Alert> {
+        if self.is_active() {
+            return None;
+        }
+        debug!("neutralization started at {} ({:?})", start, cause);
+        self.periods.push(Neutralization {
+            start,
+            end: None,
+            cause,
+        });
+        let text = match cause {
+            Cause::SessionPhase => String::from("session neutralized"),
+            Cause::SpeedDrop => String::from("field slowed — safety car or full course yellow"),
+        };
+        Some(Alert::warning(start, text))
+    }
+
+    fn end(&mut self, end: f32) -> Option<Alert> {
+        let period = self
+            .periods
+            .last_mut()
+            .filter(|period| period.end.is_none())?;
+        debug!("neutralization ended at {}", end);
+        period.end = Some(end);
+        self.slow_since = None;
+        self.fast_since = None;
+        Some(Alert::info(end, String::from("green flag, racing resumed")))
+    }
+
+    pub fn green_flag(&mut self, session_time: f32) -> Option<Alert> {
+        self.end(session_time)
+    }
+
+    /// feeds the session phase and the current speed of every car on track
+    pub fn update(
+        &mut self,
+        session_time: f32,
+        phase: udp::SessionPhase,
+        speeds: &mut [u16],
+    ) -> Option<Alert> {
+        if phase != udp::SessionPhase::Session {
+            return match phase {
+                udp::SessionPhase::FormationLap => self.start(session_time, Cause::SessionPhase),
+                _ => None,
+            };
+        }
+        if self
+            .current()
+            .is_some_and(|period| period.cause == Cause::SessionPhase)
+        {
+            return self.end(session_time);
+        }
+
+        if speeds.len() < MIN_CARS {
+            return None;
+        }
+        speeds.sort_unstable();
+        let field = speeds[speeds.len() / 2] as f32;
+        self.field_kmh = Some(field);
+        let Some(baseline) = self.baseline_kmh else {
+            self.baseline_kmh = Some(field);
+            return None;
+        };
+
+        if self.is_active() {
+            if field < baseline * GREEN_FRACTION {
+                self.fast_since = None;
+                return None;
+            }
+            let since = *self.fast_since.get_or_insert(session_time);
+            if session_time - since >= CONFIRM_MS {
+                return self.end(since);
+            }
+        } else {
+            if field > baseline * SLOW_FRACTION {
+                self.slow_since = None;
+                self.baseline_kmh = Some(baseline + (field - baseline) * BASELINE_WEIGHT);
+                return None;
+            }
+            let since = *self.slow_since.get_or_insert(session_time);
+            if session_time - since >= CONFIRM_MS {
+                return self.start(since, Cause::SpeedDrop);
+            }
+        }
+        None
+    }
+}
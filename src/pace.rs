@@ -0,0 +1,118 @@
+//! Module for pace analysis
+//!
+//! Lap time spread, rolling pace, consistency and a fuel corrected pace
+//! trend for a car, to tell real pace from a light fuel load.
+
+use crate::{
+    car_models::CarClass,
+    session::{Car, PACE_WINDOW},
+};
+
+/// lap time a liter of fuel costs in ms
+const FUEL_EFFECT_MS_PER_LITER: f32 = 30.0;
+/// fuel use assumed for other cars when ours has not been measured (liters)
+pub const DEFAULT_FUEL_PER_LAP: f32 = 3.0;
+
+#[derive(Debug, Clone)]
+pub struct PaceLap {
+    pub number: u16,
+    pub laptime_ms: u32,
+    /// counts for pace, see `Car::clean_laps`
+    pub clean: bool,
+    /// laps since the car last left the pits
+    pub stint_lap: u16,
+    /// lap time with the fuel burnt since the stop put back in, in ms
+    pub fuel_corrected_ms: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pace {
+    pub car_index: u16,
+    pub race_number: u32,
+    pub class: CarClass,
+    pub laps: Vec<PaceLap>,
+    /// (lap, rolling average of the last clean laps in ms)
+    pub rolling: Vec<(u16, f32)>,
+    pub mean_ms: Option<f32>,
+    /// standard deviation of the clean laps in ms
+    pub stddev_ms: Option<f32>,
+    /// change of the fuel corrected lap time per lap in ms, positive when
+    /// the car is getting slower for other reasons than fuel
+    pub fuel_corrected_trend: Option<f32>,
+}
+
+/// least squares slope of `points`
+fn slope(points: &[(f32, f32)]) -> Option<f32> {
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x) * (x - mean_x);
+    }
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+/// pace figures of `car`, assuming it uses `fuel_per_lap` liters a lap
+pub fn analyze(car: &Car, fuel_per_lap: f32) -> Pace {
+    let clean: Vec<u16> = car.clean_laps().map(|lap| lap.number).collect();
+    let stints = car.stints();
+    let laps: Vec<PaceLap> = car
+        .laps
+        .iter()
+        .filter(|lap| lap.info.laptime_ms > 0)
+        .map(|lap| {
+            // same boundaries as the stints, the in lap ends a stint
+            let stint_start = stints
+                .iter()
+                .find(|(first, last)| (*first..=*last).contains(&lap.number))
+                .map_or(1, |(first, _)| *first);
+            let stint_lap = lap.number + 1 - stint_start;
+            PaceLap {
+                number: lap.number,
+                laptime_ms: lap.info.laptime_ms,
+                clean: clean.contains(&lap.number),
+                stint_lap,
+                fuel_corrected_ms: lap.info.laptime_ms as f32
+                    + stint_lap as f32 * fuel_per_lap * FUEL_EFFECT_MS_PER_LITER,
+            }
+        })
+        .collect();
+
+    let clean_laps: Vec<&PaceLap> = laps.iter().filter(|lap| lap.clean).collect();
+    let rolling = (0..clean_laps.len())
+        .map(|i| {
+            let window = &clean_laps[i.saturating_sub(PACE_WINDOW - 1)..=i];
+            let average =
+                window.iter().map(|lap| lap.laptime_ms as f32).sum::<f32>() / window.len() as f32;
+            (clean_laps[i].number, average)
+        })
+        .collect();
+
+    let times: Vec<f32> = clean_laps.iter().map(|lap| lap.laptime_ms as f32).collect();
+    let mean_ms = (!times.is_empty()).then(|| times.iter().sum::<f32>() / times.len() as f32);
+    let stddev_ms = mean_ms.filter(|_| times.len() >= 2).map(|mean| {
+        let variance =
+            times.iter().map(|t| (t - mean) * (t - mean)).sum::<f32>() / (times.len() - 1) as f32;
+        variance.sqrt()
+    });
+    let corrected: Vec<(f32, f32)> = clean_laps
+        .iter()
+        .map(|lap| (lap.number as f32, lap.fuel_corrected_ms))
+        .collect();
+
+    Pace {
+        car_index: car.car_info.car_index,
+        race_number: car.car_info.race_number,
+        class: car.class(),
+        laps,
+        rolling,
+        mean_ms,
+        stddev_ms,
+        fuel_corrected_trend: slope(&corrected),
+    }
+}
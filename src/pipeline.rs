@@ -0,0 +1,276 @@
+//! Module for the UDP pipeline
+//!
+//! Connects to ACC, reads packets, optionally records them and decodes
+//! them into messages for the session model. `Live` folds the messages
+//! into the session and handles session boundaries, the archive, the
+//! results reports and the spotter. Shared by the GUI, the headless logger
+//! and replays.
+
+use std::{net::SocketAddr, path::PathBuf, thread, time::Duration};
+
+use iced::futures::channel::mpsc::UnboundedSender;
+use log::{error, info, trace, warn};
+
+use crate::{
+    archive::{self, Archive},
+    capture::Recorder,
+    report,
+    session::Session,
+    spotter::{Cue, Spotter},
+    udp, Message,
+};
+#[cfg(windows)]
+use crate::{mm, tyres};
+
+pub const ACC_ADDRESS: &str = "127.0.0.1:9000";
+/// wait before registering again after a failed read, ACC may not be up yet
+const RETRY: Duration = Duration::from_secs(2);
+
+pub struct Pipeline {
+    addr: SocketAddr,
+    reader: udp::UdpReader,
+    recorder: Option<Recorder>,
+    #[cfg(windows)]
+    memory_map: mm::MMReader,
+}
+
+impl Pipeline {
+    pub fn connect(addr: SocketAddr, recorder: Option<Recorder>) -> Result<Self, String> {
+        let reader = udp::UdpReader::bind()?;
+        udp::connect(&reader.socket, addr).map_err(|e| format!("cannot connect to ACC: {}", e))?;
+        Ok(Pipeline {
+            addr,
+            reader,
+            recorder,
+            #[cfg(windows)]
+            memory_map: mm::MMReader::new(),
+        })
+    }
+
+    /// records every packet from now on
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// waits a bit and registers with ACC again
+    pub fn retry(&mut self) {
+        thread::sleep(RETRY);
+        if let Err(e) = udp::connect(&self.reader.socket, self.addr) {
+            warn!("cannot connect to ACC: {}", e);
+        }
+    }
+
+    /// blocks until the next packet and decodes it
+    pub fn next(&mut self) -> Result<Vec<Message>, String> {
+        if let Err(e) = self.reader.listen() {
+            // not a decoding problem, nothing arrives until we register again
+            self.retry();
+            return Err(e);
+        }
+        let reader = &mut self.reader;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(reader.packet())?;
+        }
+        let inbound = udp::parse(reader)?;
+        trace!("got {}", inbound.name());
+        if let udp::Inbound::RegistrationResult(registration) = &inbound {
+            info!("connected to acc!");
+            trace!("{:#?}", registration);
+            udp::request_entry_list(&reader.socket, registration.connection_id)
+                .map_err(|e| format!("could not send entrylist request: {}", e))?;
+            udp::request_track_data(&reader.socket, registration.connection_id)
+                .map_err(|e| format!("could not send trackdata request: {}", e))?;
+        }
+        #[cfg_attr(not(windows), allow(unused_mut))]
+        let mut messages: Vec<Message> = message(inbound).into_iter().collect();
+        // sample tyres and fuel at the realtime update rate
+        #[cfg(windows)]
+        if matches!(messages.first(), Some(Message::RealtimeUpdate(_))) {
+            let physics = self.memory_map.get_physics();
+            messages.push(Message::TyreSample(tyres::TyreSample::from(&physics)));
+            messages.push(Message::Fuel(physics.fuel));
+        }
+        Ok(messages)
+    }
+}
+
+/// runs the pipeline on its own thread, the socket read blocks and
+/// retries sleep, and sends every message until `sender` is closed
+pub fn spawn(addr: SocketAddr, record: Option<PathBuf>, sender: UnboundedSender<Message>) {
+    thread::spawn(move || {
+        let mut pipeline = loop {
+            match Pipeline::connect(addr, None) {
+                Ok(pipeline) => break pipeline,
+                Err(e) => {
+                    error!("{}", e);
+                    thread::sleep(RETRY);
+                }
+            }
+        };
+        if let Some(path) = record {
+            match Recorder::create(&path) {
+                Ok(recorder) => {
+                    if let Err(e) = recorder.flush_on_interrupt() {
+                        warn!("{}", e);
+                    }
+                    pipeline.record(recorder);
+                }
+                Err(e) => error!("could not record: {}", e),
+            }
+        }
+        loop {
+            match pipeline.next() {
+                Ok(messages) => {
+                    for message in messages {
+                        if sender.unbounded_send(message).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("could not read packet: {}", e),
+            }
+        }
+    });
+}
+
+/// the session model message for a decoded packet
+pub fn message(inbound: udp::Inbound) -> Option<Message> {
+    match inbound {
+        udp::Inbound::RegistrationResult(_) => None,
+        udp::Inbound::RealtimeUpdate(update) => Some(Message::RealtimeUpdate(update)),
+        udp::Inbound::RealtimeCarUpdate(update) => Some(Message::RealTimeCarUpdate(update)),
+        udp::Inbound::EntryList(entry_list) => Some(Message::EntryList(entry_list)),
+        udp::Inbound::TrackData(track_data) => Some(Message::TrackData(track_data)),
+        udp::Inbound::EntryListCar(car_info) => Some(Message::CarInfo(car_info)),
+        udp::Inbound::BroadcastingEvent(event) => Some(Message::BroadcastingEvent(event)),
+    }
+}
+
+/// feeds a message into the session model, messages that only concern the
+/// window are ignored
+pub fn apply(session: &mut Session, message: Message) {
+    match message {
+        Message::RealtimeUpdate(update) => session.apply_realtime_update(update),
+        Message::RealTimeCarUpdate(update) => session.apply_car_update(update),
+        Message::EntryList(entry_list) => session.apply_entry_list(&entry_list),
+        Message::CarInfo(car_info) => session.apply_car_info(car_info),
+        Message::TrackData(track_data) => session.apply_track_data(track_data),
+        Message::BroadcastingEvent(event) => session.apply_broadcasting_event(&event),
+        Message::TyreSample(sample) => session.tyres.apply_sample(&sample),
+        Message::Fuel(liters) => session.fuel.apply_level(liters),
+        other => trace!("ignoring {:?} outside of the window", other),
+    }
+}
+
+/// What applying a message led to besides the session update
+#[derive(Debug, Default)]
+pub struct Applied {
+    /// results report written for the message
+    pub report: Option<PathBuf>,
+    pub cues: Vec<Cue>,
+}
+
+/// The running session and what is kept next to it
+pub struct Live {
+    pub session: Session,
+    pub spotter: Spotter,
+    /// for reading, saves go through `writer`
+    archive: Option<Archive>,
+    writer: Option<archive::Writer>,
+    /// goes up with every new session, saves of one generation update the
+    /// same archive row
+    generation: u64,
+    /// directory results reports are written to, `None` writes none
+    report_dir: Option<PathBuf>,
+    /// phase the results report of the running session was written in
+    reported: Option<udp::SessionPhase>,
+}
+
+impl Live {
+    pub fn new(archive: Option<Archive>, report_dir: Option<PathBuf>, spotter: Spotter) -> Self {
+        let writer = archive.as_ref().and_then(|archive| {
+            archive
+                .reopen()
+                .inspect_err(|e| error!("could not open archive for writing: {}", e))
+                .ok()
+                .map(archive::Writer::spawn)
+        });
+        Live {
+            session: Session::new(),
+            spotter,
+            archive,
+            writer,
+            generation: 0,
+            report_dir,
+            reported: None,
+        }
+    }
+
+    pub fn archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// queues the running session for the archive, the database work
+    /// happens on the writer thread
+    pub fn archive_session(&mut self) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        if self.session.realtime.is_none() {
+            return;
+        }
+        match archive::Snapshot::take(&self.session) {
+            Ok(snapshot) => writer.save(self.generation, snapshot),
+            Err(e) => error!("could not archive session: {}", e),
+        }
+    }
+
+    /// waits for the queued archive saves, nothing is archived afterwards
+    pub fn close_archive(&mut self) {
+        self.writer = None;
+    }
+
+    /// feeds a message into the session, archives a session when it ends,
+    /// writes its results report once it is over and runs the spotter on
+    /// every session update
+    pub fn apply(&mut self, message: Message) -> Applied {
+        let mut applied = Applied::default();
+        match message {
+            Message::RealtimeUpdate(update) => {
+                let new_session = self
+                    .session
+                    .realtime
+                    .as_ref()
+                    .is_some_and(|previous| previous.session_index != update.session_index);
+                if new_session {
+                    self.archive_session();
+                    self.generation += 1;
+                    self.reported = None;
+                    self.spotter.reset();
+                }
+                self.session.apply_realtime_update(update);
+                applied.cues = self.spotter.update(&self.session);
+                let phase = report::final_phase(&self.session);
+                if phase.is_some() && phase != self.reported {
+                    applied.report = self.write_report();
+                    self.reported = phase;
+                }
+            }
+            Message::BroadcastingEvent(event) => {
+                let session_over = event.event_type == udp::BroadcastingEventType::SessionOver;
+                self.session.apply_broadcasting_event(&event);
+                if session_over {
+                    self.archive_session();
+                }
+            }
+            other => apply(&mut self.session, other),
+        }
+        applied
+    }
+
+    fn write_report(&self) -> Option<PathBuf> {
+        report::write(&self.session, self.report_dir.as_deref()?)
+            .inspect_err(|e| error!("could not write results report: {}", e))
+            .ok()
+    }
+}
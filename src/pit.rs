@@ -0,0 +1,258 @@
+//! Module for pit stop modeling
+//!
+//! Measures how much time a pit stop costs compared to staying out and
+//! predicts where a car would rejoin the field if it pitted now.
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    car_models::CarClass,
+    session::{Car, Session},
+};
+
+/// stops shorter than this are drive throughs of the pit entry or bad data
+const MIN_LANE_TIME_MS: f32 = 5_000.0;
+/// cars within this many seconds of the rejoin point are listed as traffic
+const REJOIN_WINDOW_S: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PitLossSample {
+    /// time between pit entry and pit exit in ms
+    pub lane_time: f32,
+    /// time the same part of the lap takes on track at racing pace in ms
+    pub track_time: f32,
+}
+
+/// Measured pit lane losses for the current track
+#[derive(Debug, Default)]
+pub struct PitLossModel {
+    samples: Vec<PitLossSample>,
+}
+
+impl PitLossModel {
+    /// starts from losses measured in earlier sessions
+    pub fn from_samples(samples: &[PitLossSample]) -> Self {
+        PitLossModel {
+            samples: samples.to_vec(),
+        }
+    }
+
+    pub fn samples(&self) -> &[PitLossSample] {
+        &self.samples
+    }
+    /// adds the loss of the last completed stop of `car`
+    ///
+    /// The loss is the time spent in the pit lane minus the time the car
+    /// would have needed to cover the same part of the lap at racing pace.
+    pub fn record(&mut self, car: &Car) {
+        let Some(stop) = car.pit_stops.last().filter(|stop| stop.is_stop()) else {
+            return;
+        };
+        let (Some(lane_time), Some(exit_spline), Some(pace)) =
+            (stop.lane_time(), stop.exit_spline, car.rolling_pace())
+        else {
+            return;
+        };
+        if lane_time < MIN_LANE_TIME_MS {
+            return;
+        }
+
+        let covered = (exit_spline - stop.entry_spline).rem_euclid(1.0);
+        let track_time = covered * pace;
+        debug!(
+            "car #{} pit loss {:.1}s (lane {:.1}s)",
+            car.car_info.race_number,
+            (lane_time - track_time) / 1000.0,
+            lane_time / 1000.0
+        );
+        self.samples.push(PitLossSample {
+            lane_time,
+            track_time,
+        });
+    }
+
+    /// median measured loss in ms
+    pub fn pit_loss(&self) -> Option<f32> {
+        self.pit_loss_with_slowdown(1.0)
+    }
+
+    /// median loss in ms when the field runs `slowdown` times slower than
+    /// racing pace, the pit lane speed limit does not change
+    pub fn pit_loss_with_slowdown(&self, slowdown: f32) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut losses: Vec<f32> = self
+            .samples
+            .iter()
+            .map(|sample| sample.lane_time - sample.track_time * slowdown)
+            .collect();
+        losses.sort_by(f32::total_cmp);
+        Some(losses[losses.len() / 2])
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LapRelation {
+    SameLap,
+    /// the other car is at least a lap down on us
+    Lapped,
+    /// the other car is at least a lap up on us
+    Lapping,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejoinTraffic {
+    pub car_index: u16,
+    pub race_number: u32,
+    /// seconds on track from our rejoin point, positive when ahead of us
+    pub gap: f32,
+    pub class: CarClass,
+    pub same_class: bool,
+    pub relation: LapRelation,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rejoin {
+    pub car_index: u16,
+    /// where on the lap the car would come out, 0.0 to 1.0
+    pub spline_position: f32,
+    /// loss used for the prediction in ms
+    pub pit_loss: f32,
+    /// cars around the rejoin point, closest ahead first
+    pub traffic: Vec<RejoinTraffic>,
+}
+
+/// difference between two spline positions wrapped to -0.5..0.5 of a lap
+pub fn spline_delta(from: f32, to: f32) -> f32 {
+    (to - from + 0.5).rem_euclid(1.0) - 0.5
+}
+
+/// predicts where `car_index` rejoins if it pits this lap
+pub fn predict_rejoin(session: &Session, car_index: u16) -> Option<Rejoin> {
+    let car = session.cars.get(&car_index)?;
+    let slowdown = session.neutral.slowdown();
+    let pit_loss = session.pit_model.pit_loss_with_slowdown(slowdown)?;
+    let pace = car.rolling_pace()? * slowdown;
+
+    let lost_laps = pit_loss / pace;
+    let distance = car.distance() - lost_laps;
+    let spline_position = distance.rem_euclid(1.0);
+    let class = car.class();
+
+    let mut traffic: Vec<RejoinTraffic> = session
+        .cars
+        .values()
+        .filter(|other| other.car_info.car_index != car_index && other.realtime.is_some())
+        .filter(|other| !other.location().in_pits())
+        .filter_map(|other| {
+            let gap = spline_delta(spline_position, other.spline_position()) * pace / 1000.0;
+            if gap.abs() > REJOIN_WINDOW_S {
+                return None;
+            }
+            let laps_ahead = (other.distance() - distance).round() as i32;
+            let relation = match laps_ahead {
+                0 => LapRelation::SameLap,
+                laps if laps < 0 => LapRelation::Lapped,
+                _ => LapRelation::Lapping,
+            };
+            Some(RejoinTraffic {
+                car_index: other.car_info.car_index,
+                race_number: other.car_info.race_number,
+                gap,
+                class: other.class(),
+                same_class: other.class() == class,
+                relation,
+            })
+        })
+        .collect();
+    traffic.sort_by(|a, b| b.gap.total_cmp(&a.gap));
+
+    Some(Rejoin {
+        car_index,
+        spline_position,
+        pit_loss,
+        traffic,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, replay, session::PitStop};
+
+    /// the fixture race up to `at_ms`
+    fn until(at_ms: u32) -> Session {
+        let packets: Vec<_> = fixture::short_race()
+            .into_iter()
+            .filter(|packet| packet.at_ms <= at_ms)
+            .collect();
+        replay::replayed(&packets)
+    }
+
+    #[test]
+    fn drive_through_is_not_a_stop() {
+        let mut session = replay::replayed(&fixture::short_race());
+        // #22 stops once, for 23.5 s
+        assert_eq!(session.pit_model.sample_count(), 1);
+        let car = session.cars.get_mut(&1).unwrap();
+        assert_eq!(car.car_info.race_number, 22);
+        car.pit_stops.push(PitStop {
+            lap: 3,
+            entry_time: 264_000.0,
+            exit_time: Some(295_000.0),
+            entry_spline: 0.95,
+            exit_spline: Some(0.03),
+            stationary_ms: Some(0.0),
+        });
+        assert_eq!(car.stops().count(), 1);
+        assert_eq!(car.owed_stops(2), 1);
+        let mut model = PitLossModel::default();
+        model.record(car);
+        assert_eq!(model.sample_count(), 0);
+    }
+
+    #[test]
+    fn rejoin_behind_the_car_that_passes_in_the_pit_cycle() {
+        let samples = replay::replayed(&fixture::short_race())
+            .pit_model
+            .samples()
+            .to_vec();
+        // just before #22 turns into the pit lane, half a second ahead of #7
+        let mut session = until(174_000);
+        session.pit_model = PitLossModel::from_samples(&samples);
+        let rejoin = predict_rejoin(&session, 1).unwrap();
+        let pace = session.cars[&1].rolling_pace().unwrap();
+        assert!(
+            (rejoin.pit_loss - 24_062.0).abs() < 1.0,
+            "{}",
+            rejoin.pit_loss
+        );
+        // nobody within the traffic window, #7 is well up the road by then
+        assert!(rejoin.traffic.is_empty());
+        let predicted = spline_delta(rejoin.spline_position, session.cars[&0].spline_position());
+
+        let session = until(205_500);
+        assert!(!session.cars[&1].location().in_pits());
+        let actual = spline_delta(
+            session.cars[&1].spline_position(),
+            session.cars[&0].spline_position(),
+        );
+        assert!(predicted > 0.0);
+        assert!(
+            (predicted - actual).abs() * pace < 1_000.0,
+            "predicted {} actual {}",
+            predicted,
+            actual
+        );
+    }
+}
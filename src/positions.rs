@@ -0,0 +1,307 @@
+//! Module for position changes
+//!
+//! Compares the official order between two session updates and logs every
+//! car that moved up, together with the cars it moved ahead of and whether
+//! that happened on track, through the pit cycle or from a penalty. Passes
+//! that only show in the order on track, like lapping traffic or passes
+//! outside of races, are logged from the track position as on track.
+
+use std::collections::HashMap;
+
+use crate::{
+    car_models::CarClass,
+    classification,
+    race_control::PenaltyKind,
+    session::{Car, Session},
+    udp,
+};
+
+/// a car counts as cycling through the pits this long after its stop (ms)
+const PIT_CYCLE_MS: f32 = 90_000.0;
+/// a position change this long after a penalty is put down to it (ms)
+const PENALTY_MS: f32 = 60_000.0;
+/// a pass on track needs the other car this close ahead before and behind
+/// after (fraction of a lap), otherwise one of them crossed the line or reset
+const SWAP_SPLINE: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    /// passed on track
+    OnTrack,
+    /// one of the cars was in or just out of the pits
+    PitCycle,
+    /// one of the cars was handed a penalty
+    Penalty,
+}
+
+impl ChangeKind {
+    pub const ALL: [ChangeKind; 3] = [
+        ChangeKind::OnTrack,
+        ChangeKind::PitCycle,
+        ChangeKind::Penalty,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::OnTrack => "on track",
+            ChangeKind::PitCycle => "pit cycle",
+            ChangeKind::Penalty => "penalty",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Placing {
+    position: u16,
+    class_position: u16,
+    track_position: u16,
+    spline: f32,
+}
+
+/// Official, class and track position of every classified car
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    placings: HashMap<u16, Placing>,
+}
+
+impl Snapshot {
+    pub fn take(session: &Session) -> Self {
+        let placings = classification::classify(session)
+            .into_iter()
+            .filter(|classified| classified.car.position() > 0)
+            .map(|classified| {
+                (
+                    classified.car.car_info.car_index,
+                    Placing {
+                        position: classified.car.position(),
+                        class_position: classified.class_position,
+                        track_position: classified
+                            .car
+                            .realtime
+                            .as_ref()
+                            .map_or(0, |update| update.track_position),
+                        spline: classified.car.spline_position(),
+                    },
+                )
+            })
+            .collect();
+        Snapshot { placings }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionChange {
+    /// session time (ms) the change was seen at
+    pub session_time: f32,
+    /// lap the car was on
+    pub lap: u16,
+    pub car_index: u16,
+    pub race_number: u32,
+    pub class: CarClass,
+    pub from: u16,
+    pub to: u16,
+    pub class_from: u16,
+    pub class_to: u16,
+    /// car index and race number of the cars it moved ahead of
+    pub passed: Vec<(u16, u32)>,
+    pub kind: ChangeKind,
+}
+
+impl PositionChange {
+    pub fn involves(&self, car_index: u16) -> bool {
+        self.car_index == car_index || self.passed.iter().any(|(index, _)| *index == car_index)
+    }
+
+    pub fn describe(&self) -> String {
+        let passed: Vec<String> = self
+            .passed
+            .iter()
+            .map(|(_, number)| format!("#{}", number))
+            .collect();
+        let moved = if self.from != self.to {
+            format!("P{} to P{}", self.from, self.to)
+        } else {
+            format!("P{}", self.to)
+        };
+        let class = if self.class_from != self.class_to {
+            format!(
+                ", {} P{} to P{}",
+                self.class, self.class_from, self.class_to
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "#{} {}{} past {} ({})",
+            self.race_number,
+            moved,
+            class,
+            passed.join(" "),
+            self.kind.name()
+        )
+    }
+}
+
+fn pit_cycling(car: &Car, now: f32) -> bool {
+    car.location().in_pits()
+        || car
+            .pit_stops
+            .last()
+            .is_some_and(|stop| stop.exit_time.is_none_or(|exit| now - exit < PIT_CYCLE_MS))
+}
+
+fn penalized(car: &Car, now: f32) -> bool {
+    car.penalties.last().is_some_and(|penalty| {
+        penalty.kind != PenaltyKind::Removed && now - penalty.session_time < PENALTY_MS
+    })
+}
+
+/// whether `ahead` is less than `SWAP_SPLINE` ahead of `behind` on track
+fn just_ahead(ahead: &Placing, behind: &Placing) -> bool {
+    let gap = (ahead.spline - behind.spline).rem_euclid(1.0);
+    gap > 0.0 && gap < SWAP_SPLINE
+}
+
+/// cars that moved up between `before` and `after`, best new position
+/// first, the official order only counts when `official` is set
+pub fn changes(
+    before: &Snapshot,
+    after: &Snapshot,
+    session: &Session,
+    official: bool,
+) -> Vec<PositionChange> {
+    let mut changes = if official {
+        official_changes(before, after, session)
+    } else {
+        vec![]
+    };
+    let swaps = track_swaps(before, after, session)
+        .into_iter()
+        .filter_map(|mut swap| {
+            // passes the official order already shows are logged once
+            if let Some(change) = changes.iter().find(|c| c.car_index == swap.car_index) {
+                swap.passed
+                    .retain(|(index, _)| !change.passed.iter().any(|(other, _)| other == index));
+            }
+            (!swap.passed.is_empty()).then_some(swap)
+        })
+        .collect::<Vec<_>>();
+    changes.extend(swaps);
+    changes.sort_by_key(|change| (change.to, change.car_index));
+    changes
+}
+
+/// cars that moved ahead of a car close by on track without the official
+/// order showing it
+fn track_swaps(before: &Snapshot, after: &Snapshot, session: &Session) -> Vec<PositionChange> {
+    let now = session.session_time();
+    let racing = |car: &Car| car.location() == udp::CarLocation::Track && !pit_cycling(car, now);
+    after
+        .placings
+        .iter()
+        .filter_map(|(index, placing)| {
+            let previous = before.placings.get(index)?;
+            if previous.track_position == 0 || placing.track_position >= previous.track_position {
+                return None;
+            }
+            let car = session.cars.get(index).filter(|car| racing(car))?;
+            let mut passed: Vec<(u16, u32, u16)> = after
+                .placings
+                .iter()
+                .filter(|(other, other_placing)| {
+                    before.placings.get(other).is_some_and(|other_previous| {
+                        other_previous.track_position > 0
+                            && other_previous.track_position < previous.track_position
+                            && other_placing.track_position > placing.track_position
+                            && just_ahead(other_previous, previous)
+                            && just_ahead(placing, other_placing)
+                    })
+                })
+                .filter_map(|(other, other_placing)| {
+                    let other = session.cars.get(other)?;
+                    racing(other).then_some((
+                        other.car_info.car_index,
+                        other.car_info.race_number,
+                        other_placing.track_position,
+                    ))
+                })
+                .collect();
+            if passed.is_empty() {
+                return None;
+            }
+            passed.sort_by_key(|(_, _, track_position)| *track_position);
+            Some(PositionChange {
+                session_time: now,
+                lap: car.lap_count() + 1,
+                car_index: *index,
+                race_number: car.car_info.race_number,
+                class: car.class(),
+                from: previous.position,
+                to: placing.position,
+                class_from: previous.class_position,
+                class_to: placing.class_position,
+                passed: passed
+                    .into_iter()
+                    .map(|(index, number, _)| (index, number))
+                    .collect(),
+                kind: ChangeKind::OnTrack,
+            })
+        })
+        .collect()
+}
+
+/// cars that moved up in the official order
+fn official_changes(before: &Snapshot, after: &Snapshot, session: &Session) -> Vec<PositionChange> {
+    let now = session.session_time();
+    after
+        .placings
+        .iter()
+        .filter_map(|(index, placing)| {
+            let previous = before.placings.get(index)?;
+            if placing.position >= previous.position {
+                return None;
+            }
+            let car = session.cars.get(index)?;
+            let passed_cars: Vec<&Car> = after
+                .placings
+                .iter()
+                .filter(|(other, other_placing)| {
+                    before.placings.get(other).is_some_and(|other_previous| {
+                        other_previous.position < previous.position
+                            && other_placing.position > placing.position
+                    })
+                })
+                .filter_map(|(other, _)| session.cars.get(other))
+                .collect();
+            if passed_cars.is_empty() {
+                // moved up because a car ahead left the session
+                return None;
+            }
+            let kind = if penalized(car, now) || passed_cars.iter().any(|c| penalized(c, now)) {
+                ChangeKind::Penalty
+            } else if pit_cycling(car, now) || passed_cars.iter().any(|c| pit_cycling(c, now)) {
+                ChangeKind::PitCycle
+            } else {
+                ChangeKind::OnTrack
+            };
+            let mut passed: Vec<(u16, u32)> = passed_cars
+                .iter()
+                .map(|other| (other.car_info.car_index, other.car_info.race_number))
+                .collect();
+            passed.sort_by_key(|(index, _)| after.placings[index].position);
+            Some(PositionChange {
+                session_time: now,
+                lap: car.lap_count() + 1,
+                car_index: *index,
+                race_number: car.car_info.race_number,
+                class: car.class(),
+                from: previous.position,
+                to: placing.position,
+                class_from: previous.class_position,
+                class_to: placing.class_position,
+                passed,
+                kind,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,159 @@
+//! Module for end of session projections
+//!
+//! Projects how many laps the leader will complete before the clock runs
+//! out and where every car will finish, using rolling pace and the pit
+//! stops each car still owes.
+
+use std::collections::HashMap;
+
+use crate::session::Session;
+
+#[derive(Debug, Clone)]
+pub struct CarProjection {
+    /// lap count the car will finish the session on
+    pub finishing_lap: u16,
+    /// projected official position at the flag
+    pub position: u16,
+    /// laps left including the one in progress
+    pub laps_to_go: u16,
+    /// session time (ms) the car is projected to cross the line for the last time
+    pub finish_time: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Projection {
+    pub leader: u16,
+    pub leader_laps: u16,
+    /// Maps car index to its projection
+    pub cars: HashMap<u16, CarProjection>,
+}
+
+/// projects the end of a timed session, `None` until the leader has a pace
+pub fn project(session: &Session) -> Option<Projection> {
+    let remaining = session.remaining_time();
+    let now = session.session_time();
+    let pit_loss = session.pit_model.pit_loss().unwrap_or(0.0);
+    let owed_time = |owed: u16| owed as f32 * pit_loss;
+
+    let leader = session
+        .standings()
+        .into_iter()
+        .find(|car| car.position() == 1)?;
+    let leader_pace = leader.rolling_pace()?;
+    let leader_distance = leader.distance();
+    let leader_owed = owed_time(leader.owed_stops(session.mandatory_stops));
+
+    // the lap the leader is on when the clock hits zero is the last one
+    let leader_laps =
+        (leader_distance + (remaining - leader_owed).max(0.0) / leader_pace).floor() as u16 + 1;
+    let leader_finish = now + (leader_laps as f32 - leader_distance) * leader_pace + leader_owed;
+
+    let mut cars: Vec<(u16, CarProjection)> = session
+        .cars
+        .values()
+        .filter(|car| car.realtime.is_some())
+        .filter_map(|car| {
+            let pace = car.rolling_pace()?;
+            let distance = car.distance();
+            let owed = owed_time(car.owed_stops(session.mandatory_stops));
+
+            // a car finishes the first time it crosses the line after the leader did
+            let distance_at_flag = distance + (leader_finish - now - owed).max(0.0) / pace;
+            let finishing_lap = if car.car_info.car_index == leader.car_info.car_index {
+                leader_laps
+            } else {
+                (distance_at_flag.floor() as u16 + 1).min(leader_laps)
+            };
+            let finish_time = now + (finishing_lap as f32 - distance) * pace + owed;
+            Some((
+                car.car_info.car_index,
+                CarProjection {
+                    finishing_lap,
+                    position: 0,
+                    laps_to_go: finishing_lap.saturating_sub(car.lap_count()),
+                    finish_time,
+                },
+            ))
+        })
+        .collect();
+
+    cars.sort_by(|(_, a), (_, b)| {
+        b.finishing_lap
+            .cmp(&a.finishing_lap)
+            .then(a.finish_time.total_cmp(&b.finish_time))
+    });
+    for (position, (_, car)) in cars.iter_mut().enumerate() {
+        car.position = position as u16 + 1;
+    }
+
+    Some(Projection {
+        leader: leader.car_info.car_index,
+        leader_laps,
+        cars: cars.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, replay};
+
+    /// the fixture race halfway, before #22 pits and #7 passes it
+    fn halfway() -> Session {
+        let packets: Vec<_> = fixture::short_race()
+            .into_iter()
+            .filter(|packet| packet.at_ms < 150_000)
+            .collect();
+        replay::replayed(&packets)
+    }
+
+    #[test]
+    fn projects_laps_and_positions() {
+        let session = halfway();
+        let projection = session.projection.as_ref().unwrap();
+        assert_eq!(session.cars[&projection.leader].car_info.race_number, 99);
+        // the lap in progress when the clock runs out is completed
+        assert_eq!(projection.leader_laps, 4);
+
+        let mut cars: Vec<_> = projection.cars.iter().collect();
+        cars.sort_by_key(|(_, car)| car.position);
+        let rows: Vec<(u32, u16, u16, u16)> = cars
+            .into_iter()
+            .map(|(index, car)| {
+                (
+                    session.cars[index].car_info.race_number,
+                    car.position,
+                    car.finishing_lap,
+                    car.laps_to_go,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [(99, 1, 4, 3), (22, 2, 4, 3), (7, 3, 4, 3), (46, 4, 4, 3)]
+        );
+    }
+
+    #[test]
+    fn splash_and_dash() {
+        let mut session = halfway();
+        let ours = &session.cars[&session.focused_car().unwrap()];
+        assert_eq!(ours.car_info.race_number, 7);
+        let laps = 3.0 - ours.spline_position();
+        // 3 l a lap measured over one lap
+        session.fuel.apply_level(13.0);
+        session.fuel.lap_completed();
+        session.fuel.apply_level(10.0);
+        session.fuel.lap_completed();
+        assert_eq!(session.fuel.per_lap(), Some(3.0));
+        assert_eq!(session.splash_and_dash(), None);
+
+        session.fuel.apply_level(5.0);
+        let short = session.splash_and_dash().unwrap();
+        assert!((short - (laps * 3.0 - 5.0)).abs() < 0.01, "{}", short);
+
+        // the mandatory stop still owed covers it
+        session.mandatory_stops = 1;
+        assert_eq!(session.splash_and_dash(), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Module for race control messages
+//!
+//! Collects the broadcasting events into a race control feed, remembers
+//! where accidents happened and turns the penalty messages into penalties
+//! attached to the cars.
+
+use crate::udp::BroadcastingEventType;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PenaltyKind {
+    DriveThrough,
+    StopAndGo,
+    /// seconds added to the race time
+    Time,
+    Disqualified,
+    /// an earlier penalty was taken back
+    Removed,
+    Other,
+}
+
+impl PenaltyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PenaltyKind::DriveThrough => "drive through",
+            PenaltyKind::StopAndGo => "stop and go",
+            PenaltyKind::Time => "time penalty",
+            PenaltyKind::Disqualified => "disqualified",
+            PenaltyKind::Removed => "removed",
+            PenaltyKind::Other => "penalty",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Penalty {
+    /// session time (ms) the penalty was handed out at
+    pub session_time: f32,
+    pub kind: PenaltyKind,
+    /// stop or time penalty length in seconds
+    pub duration_s: Option<u32>,
+    /// message as race control sent it
+    pub text: String,
+}
+
+/// seconds in texts like "5s", "+10 sec" or "30 seconds"
+fn parse_duration(text: &str) -> Option<u32> {
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '-' | ':' | '+'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.iter().enumerate().find_map(|(i, word)| {
+        let digits: String = word.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if digits.is_empty() {
+            return None;
+        }
+        let unit = match &word[digits.len()..] {
+            "" => words.get(i + 1).copied().unwrap_or(""),
+            unit => unit,
+        };
+        matches!(unit, "s" | "sec" | "secs" | "second" | "seconds")
+            .then(|| digits.parse().ok())
+            .flatten()
+    })
+}
+
+impl Penalty {
+    /// reads the penalty type and length out of a penalty message
+    pub fn parse(session_time: f32, text: &str) -> Self {
+        let lower = text.to_lowercase();
+        let compact: String = lower.chars().filter(|c| c.is_alphanumeric()).collect();
+        let kind = if compact.contains("removed") || compact.contains("cleared") {
+            PenaltyKind::Removed
+        } else if compact.contains("drivethrough") || compact.contains("drivethru") {
+            PenaltyKind::DriveThrough
+        } else if compact.contains("stopandgo") || compact.contains("stopgo") {
+            PenaltyKind::StopAndGo
+        } else if compact.contains("disqualif") || compact.contains("dsq") {
+            PenaltyKind::Disqualified
+        } else if compact.contains("timepenalty") || parse_duration(&lower).is_some() {
+            PenaltyKind::Time
+        } else {
+            PenaltyKind::Other
+        };
+        Penalty {
+            session_time,
+            kind,
+            duration_s: parse_duration(&lower),
+            text: text.to_string(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.duration_s {
+            Some(seconds) => format!("{} {}s", self.kind.name(), seconds),
+            None => self.kind.name().to_string(),
+        }
+    }
+}
+
+/// Where a car was when race control reported an accident
+#[derive(Debug, Clone)]
+pub struct Accident {
+    pub session_time: f32,
+    pub car_index: u16,
+    pub race_number: Option<u32>,
+    pub spline_position: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub session_time: f32,
+    pub event_type: BroadcastingEventType,
+    pub car_index: Option<u16>,
+    pub text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        for (text, expected) in [
+            ("5s", Some(5)),
+            ("stop and go 10 sec", Some(10)),
+            ("30 seconds time penalty", Some(30)),
+            ("penalty: 15secs", Some(15)),
+            ("drive through - 3 laps to serve", None),
+            ("car 7", None),
+            ("", None),
+        ] {
+            assert_eq!(parse_duration(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn penalty_messages() {
+        for (text, kind, duration_s, describe) in [
+            (
+                "Drive Through penalty for #7",
+                PenaltyKind::DriveThrough,
+                None,
+                "drive through",
+            ),
+            (
+                "Drive-thru, cutting",
+                PenaltyKind::DriveThrough,
+                None,
+                "drive through",
+            ),
+            (
+                "Stop and Go 10s penalty",
+                PenaltyKind::StopAndGo,
+                Some(10),
+                "stop and go 10s",
+            ),
+            (
+                "Stop&Go 30 seconds",
+                PenaltyKind::StopAndGo,
+                Some(30),
+                "stop and go 30s",
+            ),
+            (
+                "5s time penalty",
+                PenaltyKind::Time,
+                Some(5),
+                "time penalty 5s",
+            ),
+            ("Time penalty", PenaltyKind::Time, None, "time penalty"),
+            (
+                "+10 sec, track limits",
+                PenaltyKind::Time,
+                Some(10),
+                "time penalty 10s",
+            ),
+            (
+                "Disqualified",
+                PenaltyKind::Disqualified,
+                None,
+                "disqualified",
+            ),
+            (
+                "DSQ - ignored flags",
+                PenaltyKind::Disqualified,
+                None,
+                "disqualified",
+            ),
+            ("Penalty removed", PenaltyKind::Removed, None, "removed"),
+            (
+                "5s penalty cleared",
+                PenaltyKind::Removed,
+                Some(5),
+                "removed 5s",
+            ),
+            (
+                "Warning for track limits",
+                PenaltyKind::Other,
+                None,
+                "penalty",
+            ),
+        ] {
+            let penalty = Penalty::parse(1000.0, text);
+            assert_eq!(penalty.kind, kind, "{:?}", text);
+            assert_eq!(penalty.duration_s, duration_s, "{:?}", text);
+            assert_eq!(penalty.describe(), describe, "{:?}", text);
+            assert_eq!(penalty.text, text);
+        }
+    }
+}
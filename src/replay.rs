@@ -0,0 +1,113 @@
+//! Module for replaying capture files
+//!
+//! Decodes recorded packets and feeds them through the session model, at
+//! the recorded pace for the window or all at once for exports.
+
+use std::{path::Path, thread, time::Duration};
+
+use iced::{
+    futures::{channel::mpsc, SinkExt, Stream, StreamExt},
+    stream,
+};
+use log::{error, info, warn};
+
+use crate::{
+    capture,
+    pipeline::{self, Live},
+    session::Session,
+    spotter::{Cue, Spotter},
+    udp, Message,
+};
+
+/// decodes every packet of a capture, (ms since start, message) in order
+pub fn decode(packets: &[capture::Packet]) -> Vec<(u32, Result<udp::Inbound, String>)> {
+    let mut reader = udp::UdpReader::new();
+    packets
+        .iter()
+        .map(|packet| {
+            let inbound = reader
+                .load(&packet.bytes)
+                .and_then(|_| udp::parse(&mut reader));
+            (packet.at_ms, inbound)
+        })
+        .collect()
+}
+
+/// feeds every packet of a capture through `live` like the live pipeline,
+/// returns the spotter cues in the order they were raised
+pub fn feed(live: &mut Live, packets: &[capture::Packet]) -> Vec<Cue> {
+    let mut cues = vec![];
+    for (at_ms, inbound) in decode(packets) {
+        match inbound {
+            Ok(inbound) => {
+                if let Some(message) = pipeline::message(inbound) {
+                    cues.extend(live.apply(message).cues);
+                }
+            }
+            Err(e) => warn!("skipping packet at {} ms: {}", at_ms, e),
+        }
+    }
+    cues
+}
+
+/// the session as it stood after the last of `packets`
+pub fn replayed(packets: &[capture::Packet]) -> Session {
+    let mut live = Live::new(None, None, Spotter::default());
+    feed(&mut live, packets);
+    live.session
+}
+
+/// the session as it stood at the end of the capture
+pub fn session(path: &Path) -> Result<Session, String> {
+    let packets = capture::read(path)?;
+    let session = replayed(&packets);
+    info!("replayed {} packets from {}", packets.len(), path.display());
+    Ok(session)
+}
+
+/// plays a capture into the window, `speed` 2.0 plays twice as fast
+pub fn worker(path: &Path, speed: f32) -> impl Stream<Item = Message> {
+    let path = path.to_path_buf();
+    stream::channel(100, move |mut output| async move {
+        // the pacing sleeps on its own thread, not on the async runtime
+        let (sender, mut receiver) = mpsc::unbounded();
+        thread::spawn(move || play(&path, speed, sender));
+        while let Some(message) = receiver.next().await {
+            output.send(message).await.expect("could not send message");
+        }
+    })
+}
+
+/// sends the messages of a capture at the recorded pace until the window
+/// stops listening
+fn play(path: &Path, speed: f32, sender: mpsc::UnboundedSender<Message>) {
+    let packets = match capture::read(path) {
+        Ok(packets) => packets,
+        Err(e) => {
+            error!("could not read capture: {}", e);
+            return;
+        }
+    };
+    info!(
+        "replaying {} packets from {}",
+        packets.len(),
+        path.display()
+    );
+    let mut previous = 0;
+    for (at_ms, inbound) in decode(&packets) {
+        let wait = at_ms.saturating_sub(previous) as f32 / speed.max(0.01);
+        thread::sleep(Duration::from_millis(wait as u64));
+        previous = at_ms;
+        match inbound {
+            Ok(inbound) => {
+                if let Some(message) = pipeline::message(inbound) {
+                    if sender.unbounded_send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(e) => warn!("skipping packet at {} ms: {}", at_ms, e),
+        }
+    }
+    info!("replay finished");
+}
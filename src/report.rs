@@ -0,0 +1,385 @@
+//! Module for results reports
+//!
+//! Turns the final classification into an official looking results sheet
+//! in Markdown and HTML, followed by the position changes of the session.
+//! Everything comes from the session model, there is no wall clock or map
+//! order in the output, so the same capture always gives the same report.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use crate::{
+    classification::{self, Order},
+    race_control::PenaltyKind,
+    session::{Car, Session},
+    udp, utils,
+};
+
+const COLUMNS: [&str; 11] = [
+    "Pos",
+    "No",
+    "Team",
+    "Drivers",
+    "Car",
+    "Laps",
+    "Total time",
+    "Gap",
+    "Best lap",
+    "Stops",
+    "Penalties",
+];
+
+/// One classified car, already formatted
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub cells: [String; 11],
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassResult {
+    pub class: &'static str,
+    pub rows: Vec<Row>,
+}
+
+const CHANGE_COLUMNS: [&str; 3] = ["Lap", "Time", "Change"];
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub title: String,
+    pub classes: Vec<ClassResult>,
+    /// position log, oldest first
+    pub changes: Vec<[String; 3]>,
+}
+
+/// `m:ss.mmm`, or `h:mm:ss.mmm` from an hour on
+fn duration(ms: u32) -> String {
+    let (hours, rest) = (ms / 3_600_000, ms % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (seconds, millis) = (rest / 1000, rest % 1000);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+    } else {
+        format!("{}:{:02}.{:03}", minutes, seconds, millis)
+    }
+}
+
+/// sum of all completed lap times, `None` when laps were driven before
+/// we connected
+fn total_time(car: &Car) -> Option<u32> {
+    if car.laps.len() < car.lap_count() as usize {
+        return None;
+    }
+    Some(
+        car.laps
+            .iter()
+            .map(|lap| lap.info.laptime_ms)
+            .filter(|laptime| *laptime < i32::MAX as u32)
+            .sum(),
+    )
+}
+
+fn best_lap(car: &Car) -> Option<u32> {
+    car.laps
+        .iter()
+        .filter(|lap| !lap.info.is_invalid)
+        .map(|lap| lap.info.laptime_ms)
+        .filter(|laptime| *laptime > 0 && *laptime < i32::MAX as u32)
+        .min()
+}
+
+/// session time (ms) the car crossed the line for the last time, `None`
+/// when that lap was not seen
+fn finished_at(car: &Car) -> Option<f32> {
+    car.laps
+        .last()
+        .filter(|lap| lap.number == car.lap_count())
+        .map(|lap| lap.completed_at)
+}
+
+/// gap on completed laps, the live gap uses track position instead
+fn gap(leader: &Car, car: &Car) -> String {
+    if leader.car_info.car_index == car.car_info.car_index {
+        return String::from("-");
+    }
+    // the lap counter also covers laps driven before we connected
+    let laps_down = leader.lap_count().saturating_sub(car.lap_count());
+    match laps_down {
+        0 => {
+            let behind = match (finished_at(car), finished_at(leader)) {
+                (Some(car), Some(leader)) => Some((car - leader).max(0.0) as u32),
+                _ => total_time(car)
+                    .zip(total_time(leader))
+                    .map(|(car, leader)| car.saturating_sub(leader)),
+            };
+            behind.map_or(String::from("-"), |behind| {
+                format!("+{}.{:03}", behind / 1000, behind % 1000)
+            })
+        }
+        1 => String::from("+1 lap"),
+        laps => format!("+{} laps", laps),
+    }
+}
+
+fn penalties(car: &Car) -> String {
+    let given: Vec<String> = car
+        .penalties
+        .iter()
+        .filter(|penalty| penalty.kind != PenaltyKind::Removed)
+        .map(|penalty| penalty.describe())
+        .collect();
+    if given.is_empty() {
+        String::from("-")
+    } else {
+        given.join(", ")
+    }
+}
+
+fn drivers(car: &Car) -> String {
+    car.car_info
+        .drivers
+        .iter()
+        .map(|driver| format!("{} {}", driver.first_name, driver.last_name))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// classification per class, classes in class order
+pub fn build(session: &Session) -> Report {
+    let track = session
+        .track
+        .as_ref()
+        .map_or("unknown track", |track| track.track_name.as_str());
+    let session_type = session
+        .realtime
+        .as_ref()
+        .map_or(String::from("session"), |update| {
+            format!("{:?}", update.session_type)
+        });
+
+    let mut classes: Vec<ClassResult> = vec![];
+    let classified = classification::ordered(session, Order::Class, None);
+    for (i, entry) in classified.iter().enumerate() {
+        let class = entry.class.name();
+        if classes.last().is_none_or(|last| last.class != class) {
+            classes.push(ClassResult {
+                class,
+                rows: vec![],
+            });
+        }
+        let leader = classified[..=i]
+            .iter()
+            .find(|other| other.class == entry.class)
+            .map_or(entry.car, |other| other.car);
+        let car = entry.car;
+        let position = match entry.class_position {
+            0 => String::from("-"),
+            position => position.to_string(),
+        };
+        let row = Row {
+            cells: [
+                position,
+                car.car_info.race_number.to_string(),
+                car.car_info.team_name.clone(),
+                drivers(car),
+                car.model_name(),
+                car.lap_count().to_string(),
+                total_time(car).map_or(String::from("-"), duration),
+                gap(leader, car),
+                best_lap(car).map_or(String::from("-"), duration),
+                car.stops().count().to_string(),
+                penalties(car),
+            ],
+        };
+        if let Some(last) = classes.last_mut() {
+            last.rows.push(row);
+        }
+    }
+
+    let changes = session
+        .position_log
+        .iter()
+        .map(|change| {
+            [
+                change.lap.to_string(),
+                duration(change.session_time.max(0.0) as u32),
+                change.describe(),
+            ]
+        })
+        .collect();
+
+    Report {
+        title: format!("{} results, {}", session_type, track),
+        classes,
+        changes,
+    }
+}
+
+fn escape_markdown(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
+
+pub fn markdown(report: &Report) -> String {
+    let mut out = format!("# {}\n", report.title);
+    for class in &report.classes {
+        out.push_str(&format!("\n## {}\n\n", class.class));
+        out.push_str(&format!("| {} |\n", COLUMNS.join(" | ")));
+        out.push_str(&format!("|{}\n", "---|".repeat(COLUMNS.len())));
+        for row in &class.rows {
+            let cells: Vec<String> = row.cells.iter().map(|cell| escape_markdown(cell)).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+    if !report.changes.is_empty() {
+        out.push_str("\n## Position changes\n\n");
+        out.push_str(&format!("| {} |\n", CHANGE_COLUMNS.join(" | ")));
+        out.push_str(&format!("|{}\n", "---|".repeat(CHANGE_COLUMNS.len())));
+        for change in &report.changes {
+            let cells: Vec<String> = change.iter().map(|cell| escape_markdown(cell)).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+    out
+}
+
+fn escape_html(cell: &str) -> String {
+    cell.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn html(report: &Report) -> String {
+    let title = escape_html(&report.title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>table {{ border-collapse: collapse; }} \
+         th, td {{ border: 1px solid #999; padding: 2px 8px; }}</style>\n\
+         </head>\n<body>\n<h1>{}</h1>\n",
+        title, title
+    );
+    for class in &report.classes {
+        out.push_str(&format!("<h2>{}</h2>\n<table>\n<tr>", class.class));
+        for column in COLUMNS {
+            out.push_str(&format!("<th>{}</th>", column));
+        }
+        out.push_str("</tr>\n");
+        for row in &class.rows {
+            out.push_str("<tr>");
+            for cell in &row.cells {
+                out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    if !report.changes.is_empty() {
+        out.push_str("<h2>Position changes</h2>\n<table>\n<tr>");
+        for column in CHANGE_COLUMNS {
+            out.push_str(&format!("<th>{}</th>", column));
+        }
+        out.push_str("</tr>\n");
+        for change in &report.changes {
+            out.push_str("<tr>");
+            for cell in change {
+                out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// the phase once the session is over, cars still finishing their last
+/// lap are in by `ResultUI` so the report is written again then
+pub fn final_phase(session: &Session) -> Option<udp::SessionPhase> {
+    session
+        .realtime
+        .as_ref()
+        .map(|update| update.phase)
+        .filter(|phase| {
+            matches!(
+                phase,
+                udp::SessionPhase::SessionOver | udp::SessionPhase::ResultUI
+            )
+        })
+}
+
+/// reports directory in the data directory
+pub fn default_dir() -> PathBuf {
+    utils::data_dir().join("reports")
+}
+
+/// writes `<name>.md` and `<name>.html` to `dir`, the name only depends
+/// on the session so a rerun overwrites the same files
+pub fn write(session: &Session, dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let name = match (&session.track, &session.realtime) {
+        (Some(track), Some(update)) => format!(
+            "{}-{:?}-{}-{}",
+            track.track_name.replace(' ', "_"),
+            update.session_type,
+            update.event_index,
+            update.session_index
+        )
+        .to_lowercase(),
+        _ => String::from("session"),
+    };
+    let path = dir.join(&name);
+    write_to(session, &path)?;
+    Ok(path)
+}
+
+/// writes `path` with `.md` and `.html` extensions
+pub fn write_to(session: &Session, path: &Path) -> Result<(), String> {
+    let report = build(session);
+    fs::write(path.with_extension("md"), markdown(&report)).map_err(|e| e.to_string())?;
+    fs::write(path.with_extension("html"), html(&report)).map_err(|e| e.to_string())?;
+    info!("wrote results report {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, replay};
+
+    #[test]
+    fn short_race_report() {
+        let session = replay::replayed(&fixture::short_race());
+        let report = build(&session);
+        fixture::snapshot("short_race.md", &markdown(&report));
+        fixture::snapshot("short_race.html", &html(&report));
+    }
+
+    #[test]
+    fn no_total_time_for_laps_missed_before_connecting() {
+        // drop the realtime and car updates of the first 100 s
+        let packets: Vec<_> = fixture::short_race()
+            .into_iter()
+            .filter(|packet| packet.at_ms >= 100_000 || ![2, 3].contains(&packet.bytes[0]))
+            .collect();
+        let session = replay::replayed(&packets);
+        let report = build(&session);
+        let rows: Vec<[&str; 3]> = report
+            .classes
+            .iter()
+            .flat_map(|class| &class.rows)
+            .map(|row| [&*row.cells[1], &*row.cells[5], &*row.cells[6]])
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ["99", "3", "-"],
+                ["7", "3", "-"],
+                ["22", "3", "-"],
+                ["46", "2", "-"]
+            ]
+        );
+    }
+}
@@ -0,0 +1,660 @@
+//! Module for the live session model
+//!
+//! Folds decoded UDP messages into per car state that the views and
+//! strategy tools read from. Knows nothing about iced so it can be fed
+//! from any message source.
+
+use std::collections::{HashMap, VecDeque};
+
+use log::{debug, error, trace, warn};
+
+use crate::{
+    alerts,
+    car_models::{self, CarClass},
+    fuel, hazards, neutral, pit, positions, projection, race_control, track, traffic, tyres, udp,
+    utils, weather,
+};
+
+/// number of recent laps used for the rolling pace
+pub const PACE_WINDOW: usize = 5;
+/// how much position history is kept per car for speed estimates in ms
+const TRACE_MS: f32 = 5_000.0;
+/// below this a car in the pit lane counts as standing in its box
+const STANDSTILL_KMH: u16 = 2;
+/// pit lane passes with less time at a standstill are drive throughs
+pub const MIN_STATIONARY_MS: f32 = 1_000.0;
+
+#[derive(Debug, Clone)]
+pub struct Lap {
+    /// lap number, 1 indexed
+    pub number: u16,
+    pub info: udp::LapInfo,
+    /// session time (ms) the lap was completed at
+    pub completed_at: f32,
+    pub position: u16,
+    pub cup_position: u16,
+    /// driven at least partly under safety car or full course yellow
+    pub neutralized: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PitStop {
+    /// lap the car was on when it entered the pit lane
+    pub lap: u16,
+    pub entry_time: f32,
+    pub exit_time: Option<f32>,
+    /// spline position at pit entry and exit
+    pub entry_spline: f32,
+    pub exit_spline: Option<f32>,
+    /// time spent at a standstill in the pit lane in ms, `None` for stops
+    /// archived before it was measured
+    pub stationary_ms: Option<f32>,
+}
+
+impl PitStop {
+    /// time spent between pit entry and pit exit in ms
+    pub fn lane_time(&self) -> Option<f32> {
+        self.exit_time.map(|exit| exit - self.entry_time)
+    }
+
+    /// completed visit where the car actually stopped, not a drive through
+    pub fn is_stop(&self) -> bool {
+        self.exit_time.is_some()
+            && self
+                .stationary_ms
+                .is_none_or(|stationary| stationary >= MIN_STATIONARY_MS)
+    }
+}
+
+#[derive(Debug)]
+pub struct Car {
+    pub car_info: udp::CarInfo,
+    pub laps: Vec<Lap>,
+    pub pit_stops: Vec<PitStop>,
+    /// last realtime update received for this car
+    pub realtime: Option<udp::RealtimeCarUpdate>,
+    /// recent (session time, distance) samples
+    pub trace: VecDeque<(f32, f32)>,
+    /// penalties handed to the car, oldest first
+    pub penalties: Vec<race_control::Penalty>,
+}
+
+impl Car {
+    pub fn new(car_info: udp::CarInfo) -> Self {
+        Car {
+            car_info,
+            laps: vec![],
+            pit_stops: vec![],
+            realtime: None,
+            trace: VecDeque::new(),
+            penalties: vec![],
+        }
+    }
+
+    /// speed over the recent trace in laps per ms
+    pub fn speed(&self) -> Option<f32> {
+        let (first, last) = (self.trace.front()?, self.trace.back()?);
+        let elapsed = last.0 - first.0;
+        if elapsed <= 0.0 {
+            return None;
+        }
+        Some((last.1 - first.1) / elapsed).filter(|speed| *speed >= 0.0)
+    }
+
+    pub fn class(&self) -> CarClass {
+        CarClass::from_model(self.car_info.car_model_type)
+    }
+
+    pub fn model_name(&self) -> String {
+        car_models::name(self.car_info.car_model_type)
+    }
+
+    pub fn position(&self) -> u16 {
+        self.realtime.as_ref().map_or(0, |update| update.position)
+    }
+
+    pub fn lap_count(&self) -> u16 {
+        self.realtime.as_ref().map_or(0, |update| update.laps)
+    }
+
+    pub fn spline_position(&self) -> f32 {
+        self.realtime
+            .as_ref()
+            .map_or(0.0, |update| update.spline_position)
+    }
+
+    /// completed laps plus the fraction of the current one
+    pub fn distance(&self) -> f32 {
+        self.lap_count() as f32 + self.spline_position()
+    }
+
+    /// completed pit stops, drive throughs are left out
+    pub fn stops(&self) -> impl Iterator<Item = &PitStop> {
+        self.pit_stops.iter().filter(|stop| stop.is_stop())
+    }
+
+    /// mandatory stops the car has not made yet
+    pub fn owed_stops(&self, mandatory_stops: u16) -> u16 {
+        mandatory_stops.saturating_sub(self.stops().count() as u16)
+    }
+
+    /// (first lap, last lap) of every stint, a stint runs from the lap
+    /// after a stop up to and including the in lap of the next one
+    pub fn stints(&self) -> Vec<(u16, u16)> {
+        let mut stints = vec![];
+        let mut first_lap = 1;
+        let last_laps = self
+            .stops()
+            .map(|stop| stop.lap + 1)
+            .chain(std::iter::once(self.lap_count()));
+        for last_lap in last_laps {
+            if last_lap >= first_lap {
+                stints.push((first_lap, last_lap));
+                first_lap = last_lap + 1;
+            }
+        }
+        stints
+    }
+
+    pub fn location(&self) -> udp::CarLocation {
+        self.realtime
+            .as_ref()
+            .map_or(udp::CarLocation::None, |update| update.car_location)
+    }
+
+    /// laps that represent racing pace, no in/out laps, invalid laps or
+    /// laps under neutralization
+    pub fn clean_laps(&self) -> impl DoubleEndedIterator<Item = &Lap> {
+        self.laps.iter().filter(|lap| {
+            !lap.info.is_invalid
+                && !lap.neutralized
+                && lap.info.lap_type == udp::LapType::Regular
+                && lap.info.laptime_ms > 0
+        })
+    }
+
+    /// average of the last `PACE_WINDOW` clean laps in ms
+    pub fn rolling_pace(&self) -> Option<f32> {
+        let recent: Vec<u32> = self
+            .clean_laps()
+            .rev()
+            .take(PACE_WINDOW)
+            .map(|lap| lap.info.laptime_ms)
+            .collect();
+        if recent.is_empty() {
+            self.realtime
+                .as_ref()
+                .map(|update| update.best_session_lap.laptime_ms)
+                .filter(|laptime| *laptime > 0 && *laptime < i32::MAX as u32)
+                .map(|laptime| laptime as f32)
+        } else {
+            Some(recent.iter().sum::<u32>() as f32 / recent.len() as f32)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Session {
+    /// Maps car index to `Car` struct
+    pub cars: HashMap<u16, Car>,
+    pub track: Option<udp::TrackData>,
+    /// what we know about the current track from earlier sessions
+    pub track_record: Option<track::TrackRecord>,
+    /// last session wide update
+    pub realtime: Option<udp::RealtimeUpdate>,
+    pub pit_model: pit::PitLossModel,
+    /// pit stops every car has to make during the session
+    pub mandatory_stops: u16,
+    /// tyre sets used by our car
+    pub tyres: tyres::TyreTracker,
+    /// fuel use of our car
+    pub fuel: fuel::FuelTracker,
+    pub weather: weather::Weather,
+    pub neutral: neutral::NeutralTracker,
+    /// cars about to catch our car, soonest first
+    pub traffic: Vec<traffic::Warning>,
+    /// stopped and slow cars on track
+    pub hazards: hazards::HazardTracker,
+    /// positions at the last session update
+    positions: positions::Snapshot,
+    /// every position change of the race, oldest first
+    pub position_log: Vec<positions::PositionChange>,
+    /// race control feed, oldest first
+    pub race_control: Vec<race_control::Entry>,
+    pub accidents: Vec<race_control::Accident>,
+    pub alerts: Vec<alerts::Alert>,
+    /// end of session projection as of the last session update
+    pub projection: Option<projection::Projection>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// current session time in ms
+    pub fn session_time(&self) -> f32 {
+        self.realtime
+            .as_ref()
+            .map_or(0.0, |update| update.session_time)
+    }
+
+    /// time left until the session clock runs out in ms
+    pub fn remaining_time(&self) -> f32 {
+        self.realtime.as_ref().map_or(0.0, |update| {
+            (update.session_end_time - update.session_time).max(0.0)
+        })
+    }
+
+    /// session running and not neutralized, the only time cars are at speed
+    pub fn green(&self) -> bool {
+        self.realtime
+            .as_ref()
+            .is_some_and(|update| update.phase == udp::SessionPhase::Session)
+            && !self.neutral.is_active()
+    }
+
+    /// car index of the car the broadcast is focused on, this is "our" car
+    pub fn focused_car(&self) -> Option<u16> {
+        self.realtime
+            .as_ref()
+            .and_then(|update| u16::try_from(update.focused_car_index).ok())
+            .filter(|index| self.cars.contains_key(index))
+    }
+
+    /// cars ordered by official position, cars without a position go last,
+    /// ties are broken by car index so the order does not depend on the map
+    pub fn standings(&self) -> Vec<&Car> {
+        let mut cars: Vec<&Car> = self.cars.values().collect();
+        cars.sort_by_key(|car| {
+            let position = match car.position() {
+                0 => u16::MAX,
+                position => position,
+            };
+            (position, car.car_info.car_index)
+        });
+        cars
+    }
+
+    pub fn apply_car_info(&mut self, car_info: udp::CarInfo) {
+        match self.cars.get_mut(&car_info.car_index) {
+            Some(car) => car.car_info = car_info,
+            None => {
+                debug!("new car #{}", car_info.race_number);
+                if car_models::lookup(car_info.car_model_type).is_none() {
+                    warn!(
+                        "car #{} has unknown model id {}, treating it as {}",
+                        car_info.race_number,
+                        car_info.car_model_type,
+                        CarClass::from_model(car_info.car_model_type)
+                    );
+                }
+                self.cars.insert(car_info.car_index, Car::new(car_info));
+            }
+        }
+    }
+
+    pub fn apply_entry_list(&mut self, entry_list: &udp::EntryList) {
+        self.cars.retain(|index, _| entry_list.cars.contains(index));
+    }
+
+    pub fn apply_track_data(&mut self, track_data: udp::TrackData) {
+        let same_track = self
+            .track_record
+            .as_ref()
+            .is_some_and(|record| record.track_id == track_data.track_id);
+        if !same_track {
+            self.save_track();
+            let record = track::TrackRecord::load_or_new(&track_data);
+            self.pit_model = pit::PitLossModel::from_samples(&record.pit_losses);
+            self.track_record = Some(record);
+        }
+        self.track = Some(track_data);
+    }
+
+    /// writes learned track geometry to disk
+    pub fn save_track(&mut self) {
+        if let Some(record) = self.track_record.as_mut() {
+            if let Err(e) = record.save() {
+                error!("could not save track record: {}", e);
+            }
+        }
+    }
+
+    pub fn apply_realtime_update(&mut self, update: udp::RealtimeUpdate) {
+        if let Some(previous) = &self.realtime {
+            if previous.session_index != update.session_index {
+                debug!("new session, clearing car history");
+                for car in self.cars.values_mut() {
+                    car.laps.clear();
+                    car.pit_stops.clear();
+                    car.realtime = None;
+                    car.trace.clear();
+                    car.penalties.clear();
+                }
+                self.neutral = neutral::NeutralTracker::default();
+                self.traffic.clear();
+                self.hazards.clear();
+                self.positions = positions::Snapshot::default();
+                self.position_log.clear();
+                self.race_control.clear();
+                self.accidents.clear();
+                self.alerts.clear();
+            }
+        }
+        if let Some(alert) = self.weather.record(&update) {
+            self.alerts.push(alert);
+        }
+        // the car updates since the last session update are complete now,
+        // the official order only means passes in a race
+        let snapshot = positions::Snapshot::take(self);
+        let official = update.session_type == udp::RaceSessionType::Race;
+        let changes = positions::changes(&self.positions, &snapshot, self, official);
+        self.position_log.extend(changes);
+        self.positions = snapshot;
+
+        let mut speeds: Vec<u16> = self
+            .cars
+            .values()
+            .filter_map(|car| car.realtime.as_ref())
+            .filter(|car| car.car_location == udp::CarLocation::Track)
+            .map(|car| car.kmh)
+            .collect();
+        let alert = self
+            .neutral
+            .update(update.session_time, update.phase, &mut speeds);
+        self.realtime = Some(update);
+        if let Some(alert) = alert {
+            self.neutralization_changed(alert);
+        }
+        self.update_traffic();
+
+        let focused = self.focused_car();
+        let green = self.green();
+        let alerts = self.hazards.update(
+            self.session_time(),
+            &self.cars,
+            focused,
+            green,
+            self.track_record.as_ref(),
+        );
+        self.alerts.extend(alerts);
+        self.update_projection();
+    }
+
+    /// recomputes the end of session projection, the views read the result
+    pub fn update_projection(&mut self) {
+        self.projection = projection::project(self);
+    }
+
+    /// liters our car is short of reaching the flag without another stop,
+    /// `None` while a mandatory stop is still owed or nothing is measured
+    pub fn splash_and_dash(&self) -> Option<f32> {
+        let car = &self.cars[&self.focused_car()?];
+        if car.owed_stops(self.mandatory_stops) > 0 {
+            return None;
+        }
+        let projected = self
+            .projection
+            .as_ref()?
+            .cars
+            .get(&car.car_info.car_index)?;
+        let laps = projected.laps_to_go as f32 - car.spline_position();
+        self.fuel.shortfall(laps).filter(|liters| *liters > 0.0)
+    }
+
+    /// warns once per car when a faster car is about to catch ours
+    fn update_traffic(&mut self) {
+        let now = self.session_time();
+        let warnings: Vec<traffic::Warning> = traffic::approaching(self)
+            .into_iter()
+            .filter(|warning| {
+                warning.time_to_catch <= traffic::WARN_S
+                    || self
+                        .traffic
+                        .iter()
+                        .any(|active| active.car_index == warning.car_index)
+            })
+            .collect();
+        for warning in &warnings {
+            if !self
+                .traffic
+                .iter()
+                .any(|active| active.car_index == warning.car_index)
+            {
+                let text = warning.describe(self.track_record.as_ref());
+                self.alerts.push(alerts::Alert::warning(now, text));
+            }
+        }
+        self.traffic = warnings;
+    }
+
+    pub fn apply_broadcasting_event(&mut self, event: &udp::BroadcastingEvent) {
+        use udp::BroadcastingEventType as Type;
+
+        let now = self.session_time();
+        let car_index = u16::try_from(event.car_id)
+            .ok()
+            .filter(|index| self.cars.contains_key(index));
+        let number = car_index
+            .and_then(|index| self.cars.get(&index))
+            .map_or(String::new(), |car| {
+                format!("#{} ", car.car_info.race_number)
+            });
+        let ours = car_index.is_some() && car_index == self.focused_car();
+        let lap_time = |ms: u32| {
+            Some(ms)
+                .filter(|ms| *ms > 0 && *ms < i32::MAX as u32)
+                .map(utils::ms_to_string)
+        };
+
+        let text = match event.event_type {
+            Type::None => {
+                trace!("{:?} event: {}", event.event_type, event.msg);
+                return;
+            }
+            // the laps themselves are picked up from the car updates
+            Type::LapCompleted => match lap_time(event.time_ms) {
+                Some(time) => format!("{}lap completed {}", number, time),
+                None => format!("{}lap completed", number),
+            },
+            Type::BestPersonalLap => match lap_time(event.time_ms) {
+                Some(time) => format!("{}personal best {}", number, time),
+                None => format!("{}personal best", number),
+            },
+            Type::GreenFlag => {
+                if let Some(alert) = self.neutral.green_flag(now) {
+                    self.neutralization_changed(alert);
+                }
+                String::from("green flag")
+            }
+            Type::SessionOver => {
+                self.alerts
+                    .push(alerts::Alert::info(now, String::from("session over")));
+                String::from("session over")
+            }
+            Type::PenaltyCommMsg => {
+                let penalty = race_control::Penalty::parse(now, &event.msg);
+                let text = format!("{}{}: {}", number, penalty.describe(), event.msg);
+                if ours {
+                    self.alerts.push(alerts::Alert::warning(now, text.clone()));
+                }
+                if let Some(car) = car_index.and_then(|index| self.cars.get_mut(&index)) {
+                    car.penalties.push(penalty);
+                }
+                text
+            }
+            Type::Accident => {
+                if let Some(car) = car_index.and_then(|index| self.cars.get(&index)) {
+                    self.accidents.push(race_control::Accident {
+                        session_time: now,
+                        car_index: car.car_info.car_index,
+                        race_number: Some(car.car_info.race_number),
+                        spline_position: car.spline_position(),
+                    });
+                }
+                let place = match (&self.track_record, self.accidents.last()) {
+                    (Some(record), Some(accident)) if car_index.is_some() => {
+                        format!(" at {}", record.describe(accident.spline_position))
+                    }
+                    _ => String::new(),
+                };
+                format!("{}accident{}", number, place)
+            }
+            Type::BestSessionLap => match lap_time(event.time_ms) {
+                Some(time) => format!("{}fastest lap {}", number, time),
+                None => format!("{}fastest lap", number),
+            },
+        };
+        debug!("race control: {}", text);
+        self.race_control.push(race_control::Entry {
+            session_time: now,
+            event_type: event.event_type,
+            car_index,
+            text,
+        });
+    }
+
+    /// re-marks laps that overlap a neutralization and points out the
+    /// cheaper pit stop while the field is slow
+    fn neutralization_changed(&mut self, alert: alerts::Alert) {
+        let now = alert.session_time;
+        self.alerts.push(alert);
+        for car in self.cars.values_mut() {
+            for lap in car.laps.iter_mut() {
+                let start = lap.completed_at - lap.info.laptime_ms as f32;
+                lap.neutralized = self.neutral.overlaps(start, lap.completed_at);
+            }
+        }
+
+        if !self.neutral.is_active() {
+            return;
+        }
+        let green = self.pit_model.pit_loss();
+        let yellow = self
+            .pit_model
+            .pit_loss_with_slowdown(self.neutral.slowdown());
+        if let (Some(green), Some(yellow)) = (green, yellow) {
+            self.alerts.push(alerts::Alert::warning(
+                now,
+                format!(
+                    "pit under yellow: {:.1}s loss instead of {:.1}s",
+                    yellow / 1000.0,
+                    green / 1000.0
+                ),
+            ));
+        }
+    }
+
+    pub fn apply_car_update(&mut self, update: udp::RealtimeCarUpdate) {
+        let now = self.session_time();
+        let green = self.green();
+        let index = update.car_index;
+        let Some(car) = self.cars.get_mut(&index) else {
+            trace!("update for unknown car {}", index);
+            return;
+        };
+        if green {
+            self.hazards.sample(&update);
+        }
+        if let Some(record) = self.track_record.as_mut() {
+            record.add_sample(&update);
+            if let Some(previous) = &car.realtime {
+                let completed = |lap: &udp::LapInfo| {
+                    lap.lap_splits
+                        .iter()
+                        .take_while(|split| **split > 0)
+                        .count()
+                };
+                let before = completed(&previous.current_lap);
+                let after = completed(&update.current_lap);
+                if after == before + 1 && update.laps == previous.laps {
+                    record.add_sector_end(before, update.spline_position);
+                }
+            }
+        }
+
+        let mut lap_completed = false;
+        let mut pit_exit = false;
+        if let Some(previous) = &car.realtime {
+            if update.laps > previous.laps {
+                car.laps.push(Lap {
+                    number: update.laps,
+                    info: update.last_lap.clone(),
+                    completed_at: now,
+                    position: update.position,
+                    cup_position: update.cup_position,
+                    neutralized: self
+                        .neutral
+                        .overlaps(now - update.last_lap.laptime_ms as f32, now),
+                });
+                lap_completed = true;
+            }
+
+            let was_in_pits = previous.car_location.in_pits();
+            let in_pits = update.car_location.in_pits();
+            if !was_in_pits && in_pits {
+                car.pit_stops.push(PitStop {
+                    lap: update.laps,
+                    entry_time: now,
+                    exit_time: None,
+                    entry_spline: update.spline_position,
+                    exit_spline: None,
+                    stationary_ms: Some(0.0),
+                });
+            } else if was_in_pits
+                && in_pits
+                && previous.kmh < STANDSTILL_KMH
+                && update.kmh < STANDSTILL_KMH
+            {
+                let since = car.trace.back().map_or(0.0, |(time, _)| now - time);
+                if let Some(stationary) = car
+                    .pit_stops
+                    .last_mut()
+                    .and_then(|stop| stop.stationary_ms.as_mut())
+                {
+                    *stationary += since.max(0.0);
+                }
+            } else if was_in_pits && !in_pits {
+                if let Some(stop) = car.pit_stops.last_mut() {
+                    stop.exit_time = Some(now);
+                    stop.exit_spline = Some(update.spline_position);
+                }
+                pit_exit = true;
+            }
+        }
+        car.realtime = Some(update);
+        let distance = car.distance();
+        if car.trace.back().is_some_and(|(_, last)| distance < *last) {
+            // lap counter and spline position disagree around the line
+            car.trace.clear();
+        }
+        car.trace.push_back((now, distance));
+        while car
+            .trace
+            .front()
+            .is_some_and(|(time, _)| now - time > TRACE_MS)
+        {
+            car.trace.pop_front();
+        }
+
+        let car = &self.cars[&index];
+        // drive throughs leave the pit loss, the stop count and the tyres alone
+        let pit_exit = pit_exit && car.pit_stops.last().is_some_and(|stop| stop.is_stop());
+        if pit_exit {
+            self.pit_model.record(car);
+            if let Some(record) = self.track_record.as_mut() {
+                record.set_pit_losses(self.pit_model.samples());
+            }
+        }
+        if self.focused_car() == Some(index) {
+            if pit_exit {
+                self.tyres.pit_stop_completed(car);
+            }
+            if lap_completed {
+                self.tyres.lap_completed(car);
+                self.fuel.lap_completed();
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Module for the audio spotter
+//!
+//! Turns session changes into short spoken cues: cars closing from
+//! behind, position changes, fastest laps, the pit window and low fuel.
+//! `Spotter` only looks at the session so the same cue stream comes out of
+//! a live session and a replay, `Voice` is what actually speaks.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    process::Command,
+    sync::mpsc,
+    thread,
+};
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{session::Session, utils};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CueKind {
+    Approaching,
+    PositionGained,
+    PositionLost,
+    FastestLap,
+    PitWindowOpen,
+    FuelCritical,
+    Hazard,
+}
+
+impl CueKind {
+    pub const ALL: [CueKind; 7] = [
+        CueKind::Approaching,
+        CueKind::PositionGained,
+        CueKind::PositionLost,
+        CueKind::FastestLap,
+        CueKind::PitWindowOpen,
+        CueKind::FuelCritical,
+        CueKind::Hazard,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    /// session time (ms) the cue was raised at
+    pub session_time: f32,
+    pub kind: CueKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CueRule {
+    pub enabled: bool,
+    /// least time between two cues of the same kind in seconds
+    pub min_interval_s: f32,
+}
+
+impl CueRule {
+    fn default_for(kind: CueKind) -> Self {
+        let min_interval_s = match kind {
+            CueKind::Approaching => 5.0,
+            CueKind::PositionGained | CueKind::PositionLost => 10.0,
+            CueKind::FastestLap => 30.0,
+            CueKind::PitWindowOpen => 0.0,
+            CueKind::FuelCritical => 120.0,
+            CueKind::Hazard => 3.0,
+        };
+        CueRule {
+            enabled: true,
+            min_interval_s,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpotterConfig {
+    pub enabled: bool,
+    /// cars catching us within this many seconds are called out
+    pub approach_s: f32,
+    /// fuel is called out below this many laps left
+    pub fuel_critical_laps: f32,
+    /// session minute the pit window opens at, when the event has one
+    pub pit_window_open_min: Option<f32>,
+    pub rules: BTreeMap<CueKind, CueRule>,
+}
+
+impl Default for SpotterConfig {
+    fn default() -> Self {
+        SpotterConfig {
+            enabled: true,
+            approach_s: 5.0,
+            fuel_critical_laps: 2.0,
+            pit_window_open_min: None,
+            rules: CueKind::ALL
+                .into_iter()
+                .map(|kind| (kind, CueRule::default_for(kind)))
+                .collect(),
+        }
+    }
+}
+
+impl SpotterConfig {
+    fn path() -> std::path::PathBuf {
+        utils::data_dir().join("spotter.json")
+    }
+
+    /// reads the user's config, writing the defaults on first start
+    pub fn load() -> Self {
+        let path = Self::path();
+        match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<SpotterConfig>(&json) {
+                Ok(config) => return config,
+                Err(e) => error!("could not read {}: {}", path.display(), e),
+            },
+            Err(_) => {
+                let config = SpotterConfig::default();
+                if let Err(e) = config.save() {
+                    warn!("could not write {}: {}", path.display(), e);
+                }
+                return config;
+            }
+        }
+        SpotterConfig::default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        fs::create_dir_all(utils::data_dir()).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(Self::path(), json).map_err(|e| e.to_string())
+    }
+
+    fn rule(&self, kind: CueKind) -> CueRule {
+        self.rules
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| CueRule::default_for(kind))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Spotter {
+    pub config: SpotterConfig,
+    /// session time of the last cue of every kind
+    last_cue: BTreeMap<CueKind, f32>,
+    /// cars already called out as closing
+    approaching: HashSet<u16>,
+    /// slow cars ahead already called out
+    hazards: HashSet<u16>,
+    position: Option<u16>,
+    /// our last lap already called out as fastest
+    fastest_lap: Option<u16>,
+    pit_window_called: bool,
+    fuel_called: bool,
+}
+
+impl Spotter {
+    pub fn new(config: SpotterConfig) -> Self {
+        Spotter {
+            config,
+            ..Spotter::default()
+        }
+    }
+
+    /// forgets what was called out, for a new session
+    pub fn reset(&mut self) {
+        *self = Spotter::new(self.config.clone());
+    }
+
+    /// cues for everything that changed since the last call
+    pub fn update(&mut self, session: &Session) -> Vec<Cue> {
+        let mut candidates = vec![];
+        let Some(car) = session
+            .focused_car()
+            .and_then(|index| session.cars.get(&index))
+        else {
+            return vec![];
+        };
+        let now = session.session_time();
+        // one shot cues stay armed while the spotter is off
+        let enabled = self.config.enabled;
+
+        let closing: HashSet<u16> = session
+            .traffic
+            .iter()
+            .filter(|warning| warning.time_to_catch <= self.config.approach_s)
+            .map(|warning| warning.car_index)
+            .collect();
+        for warning in &session.traffic {
+            if closing.contains(&warning.car_index)
+                && !self.approaching.contains(&warning.car_index)
+            {
+                candidates.push((
+                    CueKind::Approaching,
+                    format!(
+                        "{} {} behind, {:.0} seconds",
+                        warning.class, warning.race_number, warning.time_to_catch
+                    ),
+                ));
+            }
+        }
+        self.approaching = closing;
+
+        let hazards: HashSet<u16> = session
+            .hazards
+            .warned()
+            .map(|hazard| hazard.car_index)
+            .collect();
+        for hazard in session.hazards.warned() {
+            if !self.hazards.contains(&hazard.car_index) {
+                candidates.push((
+                    CueKind::Hazard,
+                    format!(
+                        "{} car ahead",
+                        if hazard.stopped { "stopped" } else { "slow" }
+                    ),
+                ));
+            }
+        }
+        self.hazards = hazards;
+
+        let position = car.position();
+        if position > 0 {
+            match self.position {
+                Some(previous) if position < previous => {
+                    candidates.push((CueKind::PositionGained, format!("P{}", position)))
+                }
+                Some(previous) if position > previous => {
+                    candidates.push((CueKind::PositionLost, format!("P{}", position)))
+                }
+                _ => (),
+            }
+            self.position = Some(position);
+        }
+
+        if let (Some(lap), Some(realtime)) = (car.laps.last(), &session.realtime) {
+            let best = realtime.best_session_lap.laptime_ms;
+            if !lap.info.is_invalid
+                && lap.info.laptime_ms > 0
+                && lap.info.laptime_ms <= best
+                && self.fastest_lap != Some(lap.number)
+            {
+                self.fastest_lap = Some(lap.number);
+                candidates.push((
+                    CueKind::FastestLap,
+                    format!("fastest lap, {}", utils::ms_to_string(lap.info.laptime_ms)),
+                ));
+            }
+        }
+
+        if let Some(open) = self.config.pit_window_open_min {
+            if enabled
+                && !self.pit_window_called
+                && now >= open * 60_000.0
+                && car.owed_stops(session.mandatory_stops) > 0
+            {
+                self.pit_window_called = true;
+                candidates.push((CueKind::PitWindowOpen, String::from("pit window open")));
+            }
+        }
+
+        let critical = self.config.fuel_critical_laps;
+        match session.fuel.laps_left() {
+            Some(laps) if enabled && laps < critical && !self.fuel_called => {
+                self.fuel_called = true;
+                candidates.push((
+                    CueKind::FuelCritical,
+                    format!("fuel critical, {:.1} laps left", laps),
+                ));
+            }
+            // re-arm after refuelling
+            Some(laps) if laps >= critical => self.fuel_called = false,
+            _ => (),
+        }
+
+        if !enabled {
+            return vec![];
+        }
+        candidates
+            .into_iter()
+            .filter_map(|(kind, text)| self.rate_limit(now, kind, text))
+            .collect()
+    }
+
+    fn rate_limit(&mut self, now: f32, kind: CueKind, text: String) -> Option<Cue> {
+        let rule = self.config.rule(kind);
+        if !rule.enabled {
+            return None;
+        }
+        let due = self
+            .last_cue
+            .get(&kind)
+            .is_none_or(|last| now - last >= rule.min_interval_s * 1000.0);
+        if !due {
+            debug!("dropping {:?} cue \"{}\"", kind, text);
+            return None;
+        }
+        self.last_cue.insert(kind, now);
+        Some(Cue {
+            session_time: now,
+            kind,
+            text,
+        })
+    }
+}
+
+/// cues waiting to be spoken, newer ones are dropped while this many wait
+const VOICE_QUEUE: usize = 2;
+
+/// Speaks cues one after another on a background thread with the speech
+/// synthesizer of the system
+pub struct Voice {
+    sender: mpsc::SyncSender<String>,
+}
+
+impl Voice {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<String>(VOICE_QUEUE);
+        thread::spawn(move || {
+            let mut working = true;
+            for text in receiver {
+                if working {
+                    working = speak(&text);
+                }
+            }
+        });
+        Voice { sender }
+    }
+
+    pub fn say(&self, cue: &Cue) {
+        info!("spotter: {}", cue.text);
+        match self.sender.try_send(cue.text.clone()) {
+            Ok(()) => (),
+            // by the time the queue is spoken the cue would be stale
+            Err(mpsc::TrySendError::Full(text)) => debug!("voice busy, dropping \"{}\"", text),
+            Err(mpsc::TrySendError::Disconnected(_)) => warn!("spotter voice has stopped"),
+        }
+    }
+}
+
+/// speaks `text`, false when there is no synthesizer to use
+fn speak(text: &str) -> bool {
+    #[cfg(windows)]
+    let status = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak('{}')",
+                text.replace('\'', "''")
+            ),
+        ])
+        .status();
+    #[cfg(not(windows))]
+    let status = Command::new("spd-say").args(["--wait", text]).status();
+
+    match status {
+        Ok(_) => true,
+        Err(e) => {
+            error!("no speech synthesizer, spotter stays silent: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{capture, fixture, pipeline::Live, replay};
+
+    fn replay(config: SpotterConfig) -> (Live, Vec<Cue>) {
+        let path = fixture::write_capture("spotter", &fixture::short_race());
+        let packets = capture::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut live = Live::new(None, None, Spotter::new(config));
+        let cues = replay::feed(&mut live, &packets);
+        (live, cues)
+    }
+
+    #[test]
+    fn replayed_race_cues() {
+        let (_, cues) = replay(SpotterConfig::default());
+        let cues: Vec<(u32, CueKind, &str)> = cues
+            .iter()
+            .map(|cue| (cue.session_time as u32, cue.kind, cue.text.as_str()))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (33_000, CueKind::PositionLost, "P3"),
+                (179_000, CueKind::PositionGained, "P2"),
+                (206_000, CueKind::Hazard, "stopped car ahead"),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_spotter_keeps_fuel_cue_armed() {
+        let (mut live, cues) = replay(SpotterConfig {
+            enabled: false,
+            ..SpotterConfig::default()
+        });
+        assert!(cues.is_empty());
+        for level in [10.0, 7.0] {
+            live.session.fuel.apply_level(level);
+            live.session.fuel.lap_completed();
+        }
+        live.session.fuel.apply_level(4.0);
+        assert!(live.spotter.update(&live.session).is_empty());
+
+        live.spotter.config.enabled = true;
+        let cues = live.spotter.update(&live.session);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].kind, CueKind::FuelCritical);
+        assert_eq!(cues[0].text, "fuel critical, 1.3 laps left");
+    }
+}
@@ -0,0 +1,339 @@
+//! Module for the track database
+//!
+//! Keeps one versioned file per track with everything learned while
+//! running there: the outline and pit lane from car world positions, the
+//! sector boundaries from lap split timing and the measured pit losses.
+//! Every later session on the same track starts from that file.
+
+use std::{fs, path::PathBuf};
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{pit::PitLossSample, udp, utils};
+
+/// current version of the track files, bump and extend `migrate` on change
+pub const TRACK_FILE_VERSION: u32 = 2;
+/// number of spline bins the lap is divided into
+pub const BINS: usize = 500;
+/// a learned value keeps adapting after this many samples
+const MAX_WEIGHT: u32 = 50;
+/// pit loss samples kept per track
+const MAX_PIT_SAMPLES: usize = 50;
+/// bins on either side used to measure the heading change at a bin
+const CURVATURE_SPAN: usize = 3;
+/// heading change per bin (radians) above which the line counts as turning
+const CORNER_CURVATURE: f32 = 0.04;
+/// smallest total heading change (radians) that makes a corner
+const CORNER_MIN_TURN: f32 = 0.35;
+/// outline share needed before corners are numbered
+const CORNER_MIN_COVERAGE: f32 = 0.95;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OutlinePoint {
+    pub x: f32,
+    pub y: f32,
+    pub samples: u32,
+}
+
+impl OutlinePoint {
+    fn add(&mut self, x: f32, y: f32) {
+        self.samples += 1;
+        let weight = 1.0 / self.samples.min(MAX_WEIGHT) as f32;
+        self.x += (x - self.x) * weight;
+        self.y += (y - self.y) * weight;
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.samples > 0
+    }
+}
+
+/// Spline position where a sector ends, learned from split timing
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SectorBoundary {
+    pub spline_position: f32,
+    pub samples: u32,
+}
+
+impl SectorBoundary {
+    fn add(&mut self, spline_position: f32) {
+        self.samples += 1;
+        let weight = 1.0 / self.samples.min(MAX_WEIGHT) as f32;
+        self.spline_position += (spline_position - self.spline_position) * weight;
+    }
+}
+
+fn version_one() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackRecord {
+    /// files written before the version field existed are version 1
+    #[serde(default = "version_one")]
+    pub version: u32,
+    pub track_id: u32,
+    pub track_name: String,
+    #[serde(default)]
+    pub track_meters: u32,
+    /// racing line, `BINS` points evenly spaced by spline position
+    pub racing_line: Vec<OutlinePoint>,
+    /// pit lane, binned like the racing line, only the pit part is known
+    pub pit_lane: Vec<OutlinePoint>,
+    /// ends of sector 1 and 2, sector 3 ends on the line
+    #[serde(default)]
+    pub sectors: Vec<SectorBoundary>,
+    /// measured pit stops, newest last
+    #[serde(default)]
+    pub pit_losses: Vec<PitLossSample>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+fn bin(spline_position: f32) -> usize {
+    ((spline_position.rem_euclid(1.0) * BINS as f32) as usize).min(BINS - 1)
+}
+
+fn tracks_dir() -> PathBuf {
+    utils::data_dir().join("tracks")
+}
+
+/// upgrades a record read from an older file to the current version
+fn migrate(mut record: TrackRecord) -> Result<TrackRecord, String> {
+    if record.version > TRACK_FILE_VERSION {
+        return Err(format!(
+            "track file version {} is newer than supported {}",
+            record.version, TRACK_FILE_VERSION
+        ));
+    }
+    if record.version < 2 {
+        // version 1 only had the outline, the new fields default to empty
+        record.version = 2;
+        record.dirty = true;
+    }
+    if record.racing_line.len() != BINS || record.pit_lane.len() != BINS {
+        return Err(String::from("track file has a different bin count"));
+    }
+    Ok(record)
+}
+
+fn read(path: &PathBuf) -> Result<TrackRecord, String> {
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let record = serde_json::from_str::<TrackRecord>(&json).map_err(|e| e.to_string())?;
+    migrate(record)
+}
+
+/// every track in the database
+pub fn catalog() -> Vec<TrackRecord> {
+    let Ok(entries) = fs::read_dir(tracks_dir()) else {
+        return vec![];
+    };
+    let mut records: Vec<TrackRecord> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| match read(&path) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn!("skipping {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    records.sort_by(|a, b| a.track_name.cmp(&b.track_name));
+    records
+}
+
+impl TrackRecord {
+    pub fn new(track_data: &udp::TrackData) -> Self {
+        TrackRecord {
+            version: TRACK_FILE_VERSION,
+            track_id: track_data.track_id,
+            track_name: track_data.track_name.clone(),
+            track_meters: track_data.track_meters,
+            racing_line: vec![OutlinePoint::default(); BINS],
+            pit_lane: vec![OutlinePoint::default(); BINS],
+            sectors: vec![],
+            pit_losses: vec![],
+            dirty: true,
+        }
+    }
+
+    fn path(track_id: u32) -> PathBuf {
+        tracks_dir().join(format!("{}.json", track_id))
+    }
+
+    /// loads the stored record by id, then by name, or starts a new one
+    pub fn load_or_new(track_data: &udp::TrackData) -> Self {
+        let path = Self::path(track_data.track_id);
+        let stored = match read(&path) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                if path.exists() {
+                    error!("could not read {}: {}", path.display(), e);
+                }
+                catalog()
+                    .into_iter()
+                    .find(|record| record.track_name == track_data.track_name)
+            }
+        };
+        match stored {
+            Some(mut record) => {
+                info!("loaded track record for {}", record.track_name);
+                if record.track_id != track_data.track_id
+                    || record.track_meters != track_data.track_meters
+                {
+                    record.track_id = track_data.track_id;
+                    record.track_meters = track_data.track_meters;
+                    record.dirty = true;
+                }
+                record
+            }
+            None => Self::new(track_data),
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        fs::create_dir_all(tracks_dir()).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(Self::path(self.track_id), json).map_err(|e| e.to_string())?;
+        debug!("saved track record for {}", self.track_name);
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn add_sample(&mut self, update: &udp::RealtimeCarUpdate) {
+        let line = match update.car_location {
+            udp::CarLocation::Track => &mut self.racing_line,
+            location if location.in_pits() => &mut self.pit_lane,
+            _ => return,
+        };
+        line[bin(update.spline_position)].add(update.world_pos_x, update.world_pos_y);
+        self.dirty = true;
+    }
+
+    /// records where sector `sector` (0 based) ended for a car
+    pub fn add_sector_end(&mut self, sector: usize, spline_position: f32) {
+        if sector >= 2 {
+            return;
+        }
+        while self.sectors.len() <= sector {
+            self.sectors.push(SectorBoundary::default());
+        }
+        self.sectors[sector].add(spline_position);
+        self.dirty = true;
+    }
+
+    pub fn set_pit_losses(&mut self, samples: &[PitLossSample]) {
+        let start = samples.len().saturating_sub(MAX_PIT_SAMPLES);
+        self.pit_losses = samples[start..].to_vec();
+        self.dirty = true;
+    }
+
+    /// share of the racing line that has been driven over
+    pub fn coverage(&self) -> f32 {
+        self.racing_line.iter().filter(|p| p.is_known()).count() as f32 / BINS as f32
+    }
+
+    /// world position of a spline position on the racing line
+    pub fn point_at(&self, spline_position: f32) -> Option<(f32, f32)> {
+        let point = self.racing_line[bin(spline_position)];
+        point.is_known().then_some((point.x, point.y))
+    }
+
+    /// heading change per bin along the racing line, `None` for unknown bins
+    fn curvature(&self) -> Vec<Option<f32>> {
+        let point = |i: usize| self.racing_line[i % BINS];
+        (0..BINS)
+            .map(|i| {
+                let (before, here, after) = (
+                    point(i + BINS - CURVATURE_SPAN),
+                    point(i),
+                    point(i + CURVATURE_SPAN),
+                );
+                if !(before.is_known() && here.is_known() && after.is_known()) {
+                    return None;
+                }
+                let incoming = (here.y - before.y).atan2(here.x - before.x);
+                let outgoing = (after.y - here.y).atan2(after.x - here.x);
+                let turn = (outgoing - incoming + std::f32::consts::PI)
+                    .rem_euclid(std::f32::consts::TAU)
+                    - std::f32::consts::PI;
+                Some(turn.abs() / CURVATURE_SPAN as f32)
+            })
+            .collect()
+    }
+
+    /// spline positions of corner apexes, T1 first from the start line
+    pub fn corners(&self) -> Vec<f32> {
+        if self.coverage() < CORNER_MIN_COVERAGE {
+            return vec![];
+        }
+        let curvature = self.curvature();
+        let mut corners = vec![];
+        let mut i = 0;
+        while i < BINS {
+            if curvature[i].unwrap_or(0.0) < CORNER_CURVATURE {
+                i += 1;
+                continue;
+            }
+            let (mut apex, mut sharpest, mut turn) = (i, 0.0, 0.0);
+            while i < BINS && curvature[i].unwrap_or(0.0) >= CORNER_CURVATURE {
+                let value = curvature[i].unwrap_or(0.0);
+                turn += value;
+                if value > sharpest {
+                    sharpest = value;
+                    apex = i;
+                }
+                i += 1;
+            }
+            if turn >= CORNER_MIN_TURN {
+                corners.push((apex as f32 + 0.5) / BINS as f32);
+            }
+        }
+        corners
+    }
+
+    /// readable name for a spline position, the nearest corner if there is
+    /// one close by, otherwise the distance into the lap
+    pub fn describe(&self, spline_position: f32) -> String {
+        let nearest = self
+            .corners()
+            .into_iter()
+            .enumerate()
+            .map(|(i, apex)| {
+                (
+                    i,
+                    (apex - spline_position)
+                        .abs()
+                        .min(1.0 - (apex - spline_position).abs()),
+                )
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((i, distance)) if distance < 0.03 => format!("T{}", i + 1),
+            _ if self.track_meters > 0 => format!(
+                "{:.0} m",
+                spline_position.rem_euclid(1.0) * self.track_meters as f32
+            ),
+            _ => format!("{:.0}%", spline_position.rem_euclid(1.0) * 100.0),
+        }
+    }
+
+    /// sector (0 based) a spline position falls into, once boundaries are known
+    pub fn sector_at(&self, spline_position: f32) -> Option<usize> {
+        if self.sectors.len() < 2 || self.sectors.iter().any(|s| s.samples == 0) {
+            return None;
+        }
+        Some(
+            self.sectors
+                .iter()
+                .take_while(|boundary| spline_position >= boundary.spline_position)
+                .count(),
+        )
+    }
+}
@@ -0,0 +1,119 @@
+//! Module for blue flag traffic
+//!
+//! Predicts when a faster class car, or any car a lap or more up on us,
+//! catches our car, from the closing speed of the two cars over the last
+//! seconds.
+
+use crate::{
+    car_models::CarClass,
+    pit::spline_delta,
+    session::{Car, Session},
+    track::TrackRecord,
+};
+
+/// a warning is raised when a car catches us within this many seconds
+pub const WARN_S: f32 = 10.0;
+/// a raised warning stays up until the catch is this far away again
+pub const RELEASE_S: f32 = 15.0;
+/// cars further behind than this share of a lap are not looked at
+const LOOK_BEHIND: f32 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub car_index: u16,
+    pub race_number: u32,
+    pub class: CarClass,
+    /// the car is at least a lap up on us
+    pub lapping: bool,
+    /// gap on track in meters, when the track length is known
+    pub gap_meters: Option<f32>,
+    /// seconds until the car reaches us
+    pub time_to_catch: f32,
+    /// where on the lap the car reaches us, 0.0 to 1.0
+    pub catch_spline: f32,
+}
+
+impl Warning {
+    /// alert text like "GT3 #12 closing, 3.5s, catch at T6"
+    pub fn describe(&self, record: Option<&TrackRecord>) -> String {
+        let place = match record {
+            Some(record) => record.describe(self.catch_spline),
+            None => format!("{:.0}%", self.catch_spline * 100.0),
+        };
+        format!(
+            "{} #{} {}closing, {:.1}s, catch at {}",
+            self.class,
+            self.race_number,
+            if self.lapping { "lapping, " } else { "" },
+            self.time_to_catch,
+            place
+        )
+    }
+}
+
+fn is_faster(other: &Car, car: &Car) -> bool {
+    match (other.rolling_pace(), car.rolling_pace()) {
+        (Some(other), Some(ours)) => other < ours,
+        // without lap times fall back on the class order
+        _ => other.class() < car.class(),
+    }
+}
+
+/// cars behind us that catch our car within `RELEASE_S`, soonest first
+pub fn approaching(session: &Session) -> Vec<Warning> {
+    let Some(ours) = session
+        .focused_car()
+        .and_then(|index| session.cars.get(&index))
+    else {
+        return vec![];
+    };
+    if ours.realtime.is_none() || ours.location().in_pits() {
+        return vec![];
+    }
+    let Some(our_speed) = ours.speed() else {
+        return vec![];
+    };
+    let track_meters = session
+        .track
+        .as_ref()
+        .map(|track| track.track_meters as f32)
+        .filter(|meters| *meters > 0.0);
+
+    let mut warnings: Vec<Warning> = session
+        .cars
+        .values()
+        .filter(|other| other.car_info.car_index != ours.car_info.car_index)
+        .filter(|other| other.realtime.is_some() && !other.location().in_pits())
+        .filter_map(|other| {
+            let gap = spline_delta(other.spline_position(), ours.spline_position());
+            if gap <= 0.0 || gap > LOOK_BEHIND {
+                return None;
+            }
+            let lapping = other.distance() - ours.distance() > 0.5;
+            let faster_class = other.class() != ours.class() && is_faster(other, ours);
+            if !lapping && !faster_class {
+                return None;
+            }
+            let closing = other.speed()? - our_speed;
+            if closing <= 0.0 {
+                return None;
+            }
+            let time_to_catch = gap / closing / 1000.0;
+            if time_to_catch > RELEASE_S {
+                return None;
+            }
+            Some(Warning {
+                car_index: other.car_info.car_index,
+                race_number: other.car_info.race_number,
+                class: other.class(),
+                lapping,
+                gap_meters: track_meters.map(|meters| gap * meters),
+                time_to_catch,
+                catch_spline: (ours.spline_position() + our_speed * time_to_catch * 1000.0)
+                    .rem_euclid(1.0),
+            })
+        })
+        .collect();
+    warnings.sort_by(|a, b| a.time_to_catch.total_cmp(&b.time_to_catch));
+    warnings
+}
@@ -0,0 +1,264 @@
+//! Module for tyre set tracking
+//!
+//! Keeps track of the tyre sets fitted to our car, how many laps each set
+//! has done and how its pace falls off, and checks the remaining stint plan
+//! against the sets left for the event.
+
+use log::{debug, warn};
+
+use crate::{mm, session::Car, udp};
+
+/// dry sets ACC hands out for a race weekend by default
+const DEFAULT_DRY_SETS: u16 = 5;
+/// laps a new stint is planned with
+const DEFAULT_STINT_LAPS: u16 = 25;
+/// stationary time a stop needs before it can have included a tyre change
+const MIN_TYRE_CHANGE_MS: f32 = 20_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compound {
+    Dry,
+    Wet,
+}
+
+/// Tyre readings from the shared memory physics page
+#[derive(Debug, Clone, Copy)]
+pub struct TyreSample {
+    pub pressure: [f32; 4],
+    pub core_temp: [f32; 4],
+}
+
+impl From<&mm::Physics> for TyreSample {
+    fn from(physics: &mm::Physics) -> Self {
+        TyreSample {
+            pressure: physics.wheel_pressure,
+            core_temp: physics.tyre_core_temp,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TyreSet {
+    /// 1 based order the set was fitted in
+    pub number: u16,
+    pub compound: Compound,
+    pub fitted_on_lap: u16,
+    /// every lap driven on the set, including in and out laps
+    pub laps: u16,
+    /// clean lap times on the set in ms
+    pub laptimes: Vec<u32>,
+    /// running averages of the shared memory samples, FL FR RL RR
+    pub avg_pressure: Option<[f32; 4]>,
+    pub avg_core_temp: Option<[f32; 4]>,
+    sample_count: u32,
+}
+
+impl TyreSet {
+    fn new(number: u16, compound: Compound, fitted_on_lap: u16) -> Self {
+        TyreSet {
+            number,
+            compound,
+            fitted_on_lap,
+            laps: 0,
+            laptimes: vec![],
+            avg_pressure: None,
+            avg_core_temp: None,
+            sample_count: 0,
+        }
+    }
+
+    /// lap time lost per lap on this set in ms, least squares over clean laps
+    pub fn wear_trend(&self) -> Option<f32> {
+        if self.laptimes.len() < 3 {
+            return None;
+        }
+        let n = self.laptimes.len() as f32;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = self.laptimes.iter().map(|t| *t as f32).sum::<f32>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (x, y) in self.laptimes.iter().enumerate() {
+            let dx = x as f32 - mean_x;
+            covariance += dx * (*y as f32 - mean_y);
+            variance += dx * dx;
+        }
+        Some(covariance / variance)
+    }
+
+    fn add_sample(&mut self, sample: &TyreSample) {
+        self.sample_count += 1;
+        let weight = 1.0 / self.sample_count as f32;
+        let blend = |average: Option<[f32; 4]>, value: [f32; 4]| {
+            let mut average = average.unwrap_or(value);
+            for (avg, v) in average.iter_mut().zip(value) {
+                *avg += (v - *avg) * weight;
+            }
+            Some(average)
+        };
+        self.avg_pressure = blend(self.avg_pressure, sample.pressure);
+        self.avg_core_temp = blend(self.avg_core_temp, sample.core_temp);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StintPlan {
+    pub laps: u16,
+    /// whether the stint starts on a fresh dry set
+    pub new_set: bool,
+}
+
+/// Changes the strategist makes from the tyre board
+#[derive(Debug, Clone)]
+pub enum TyreAction {
+    SetDrySets(u16),
+    /// the last stop did not change tyres, keep running the previous set
+    KeepPreviousSet,
+    SetCompound(Compound),
+    AddStint,
+    RemoveStint(usize),
+    ToggleNewSet(usize),
+    SetStintLaps(usize, u16),
+    /// forget all fitted sets, for a new event
+    Reset,
+}
+
+#[derive(Debug)]
+pub struct TyreTracker {
+    /// dry sets available for the event
+    pub dry_sets: u16,
+    pub sets: Vec<TyreSet>,
+    /// stints still to run after the current one
+    pub plan: Vec<StintPlan>,
+}
+
+impl Default for TyreTracker {
+    fn default() -> Self {
+        TyreTracker {
+            dry_sets: DEFAULT_DRY_SETS,
+            sets: vec![],
+            plan: vec![],
+        }
+    }
+}
+
+impl TyreTracker {
+    pub fn current(&self) -> Option<&TyreSet> {
+        self.sets.last()
+    }
+
+    fn fit(&mut self, compound: Compound, lap: u16) {
+        let number = self.sets.len() as u16 + 1;
+        debug!("fitted tyre set {} ({:?}) on lap {}", number, compound, lap);
+        self.sets.push(TyreSet::new(number, compound, lap));
+    }
+
+    /// stops long enough for a tyre change are assumed to fit a new set of
+    /// the current compound, shorter ones keep the set on the car
+    pub fn pit_stop_completed(&mut self, car: &Car) {
+        let Some(stop) = car.pit_stops.last() else {
+            return;
+        };
+        if stop
+            .stationary_ms
+            .is_none_or(|stationary| stationary >= MIN_TYRE_CHANGE_MS)
+        {
+            let compound = self.current().map_or(Compound::Dry, |set| set.compound);
+            self.fit(compound, car.lap_count());
+        } else {
+            debug!(
+                "stop on lap {} too short for a tyre change, keeping the set",
+                stop.lap
+            );
+        }
+        if !self.plan.is_empty() {
+            self.plan.remove(0);
+        }
+    }
+
+    pub fn lap_completed(&mut self, car: &Car) {
+        let Some(lap) = car.laps.last() else {
+            return;
+        };
+        if self.sets.is_empty() {
+            self.fit(Compound::Dry, lap.number.saturating_sub(1));
+        }
+        let set = self.sets.last_mut().unwrap();
+        set.laps += 1;
+        if !lap.info.is_invalid && lap.info.lap_type == udp::LapType::Regular {
+            set.laptimes.push(lap.info.laptime_ms);
+        }
+    }
+
+    pub fn apply_sample(&mut self, sample: &TyreSample) {
+        if let Some(set) = self.sets.last_mut() {
+            set.add_sample(sample);
+        }
+    }
+
+    pub fn dry_sets_used(&self) -> u16 {
+        self.sets
+            .iter()
+            .filter(|set| set.compound == Compound::Dry)
+            .count() as u16
+    }
+
+    pub fn remaining_dry_sets(&self) -> u16 {
+        self.dry_sets.saturating_sub(self.dry_sets_used())
+    }
+
+    pub fn planned_new_sets(&self) -> u16 {
+        self.plan.iter().filter(|stint| stint.new_set).count() as u16
+    }
+
+    /// warning when the remaining plan needs more sets than are left
+    pub fn plan_warning(&self) -> Option<String> {
+        let needed = self.planned_new_sets();
+        let remaining = self.remaining_dry_sets();
+        (needed > remaining)
+            .then(|| format!("plan needs {} new sets but only {} left", needed, remaining))
+    }
+
+    pub fn perform(&mut self, action: TyreAction) {
+        match action {
+            TyreAction::SetDrySets(sets) => self.dry_sets = sets,
+            TyreAction::KeepPreviousSet => {
+                if self.sets.len() < 2 {
+                    warn!("no previous tyre set to keep");
+                    return;
+                }
+                // fold the laps of the wrongly assumed set back into the previous one
+                let set = self.sets.pop().unwrap();
+                let previous = self.sets.last_mut().unwrap();
+                previous.laps += set.laps;
+                previous.laptimes.extend(set.laptimes);
+            }
+            TyreAction::SetCompound(compound) => {
+                if let Some(set) = self.sets.last_mut() {
+                    set.compound = compound;
+                }
+            }
+            TyreAction::AddStint => self.plan.push(StintPlan {
+                laps: DEFAULT_STINT_LAPS,
+                new_set: true,
+            }),
+            TyreAction::RemoveStint(index) => {
+                if index < self.plan.len() {
+                    self.plan.remove(index);
+                }
+            }
+            TyreAction::ToggleNewSet(index) => {
+                if let Some(stint) = self.plan.get_mut(index) {
+                    stint.new_set = !stint.new_set;
+                }
+            }
+            TyreAction::SetStintLaps(index, laps) => {
+                if let Some(stint) = self.plan.get_mut(index) {
+                    stint.laps = laps;
+                }
+            }
+            TyreAction::Reset => {
+                self.sets.clear();
+                self.plan.clear();
+            }
+        }
+    }
+}
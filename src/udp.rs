@@ -7,24 +7,20 @@
 //! let mut reader = udp::UdpReader::new();
 //! let _recv_bytes = udp::connect(&reader.socket, addr).expect("cannot connect to ACC");
 //! reader.listen().unwrap();
-//! match InboundMessageType::try_from(reader.read_u8().unwrap()).unwrap() {
-//!     InboundMessageType::RegistrationResult => {
-//!         let registration = parse_registration_result(&mut reader).unwrap();
-//!         request_entry_list(&reader.socket, registration.connection_id).unwrap();
-//!         request_track_data(&reader.socket, registration.connection_id).unwrap();
-//!     }
-//!     ...
+//! if let Inbound::RegistrationResult(registration) = parse(&mut reader).unwrap() {
+//!     request_entry_list(&reader.socket, registration.connection_id).unwrap();
+//!     request_track_data(&reader.socket, registration.connection_id).unwrap();
 //! }
 //! ```
 
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{Error, Write},
+    io::Error,
     net::{SocketAddr, UdpSocket},
 };
 
-use log::{debug, error, trace};
+use log::trace;
+use serde::Serialize;
 
 const BROADCASTING_PROTOCOL_VERSION: u8 = 4;
 
@@ -72,9 +68,9 @@ impl TryFrom<u8> for InboundMessageType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 #[repr(u8)]
-enum RaceSessionType {
+pub enum RaceSessionType {
     Practice = 0,
     Qualifying = 4,
     Superpole = 9,
@@ -103,9 +99,9 @@ impl TryFrom<u8> for RaceSessionType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 #[repr(u8)]
-enum SessionPhase {
+pub enum SessionPhase {
     None = 0,
     Starting = 1,
     PreFormation = 2,
@@ -136,7 +132,7 @@ impl TryFrom<u8> for SessionPhase {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 #[repr(u8)]
 pub enum BroadcastingEventType {
     None = 0,
@@ -167,16 +163,51 @@ impl TryFrom<u8> for BroadcastingEventType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[repr(u8)]
+pub enum CarLocation {
+    None = 0,
+    Track = 1,
+    Pitlane = 2,
+    PitEntry = 3,
+    PitExit = 4,
+}
+
+impl TryFrom<u8> for CarLocation {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CarLocation::None),
+            1 => Ok(CarLocation::Track),
+            2 => Ok(CarLocation::Pitlane),
+            3 => Ok(CarLocation::PitEntry),
+            4 => Ok(CarLocation::PitExit),
+            _ => Err("could not parse car location"),
+        }
+    }
+}
+
+impl CarLocation {
+    /// true while the car is anywhere between pit entry and pit exit
+    pub fn in_pits(&self) -> bool {
+        matches!(
+            self,
+            CarLocation::Pitlane | CarLocation::PitEntry | CarLocation::PitExit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct DriverInfo {
-    first_name: String,
-    last_name: String,
-    short_name: String,
-    category: u8, // could potentially be an enum
-    nationality: u16,
+    pub first_name: String,
+    pub last_name: String,
+    pub short_name: String,
+    pub category: u8, // could potentially be an enum
+    pub nationality: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CarInfo {
     pub car_index: u16,
     pub car_model_type: u8,
@@ -188,14 +219,14 @@ pub struct CarInfo {
     pub nationality: u16, // maybe enum
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum LapType {
     Outlap,
     Inlap,
     Regular,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LapInfo {
     pub laptime_ms: u32,
     pub car_index: u16,
@@ -206,6 +237,21 @@ pub struct LapInfo {
     pub lap_type: LapType,
 }
 
+impl LapInfo {
+    /// a lap without a time, as sent for cars that have not set one yet
+    pub fn empty(car_index: u16) -> Self {
+        LapInfo {
+            laptime_ms: i32::MAX as u32,
+            car_index,
+            driver_index: 0,
+            lap_splits: vec![0; 3],
+            is_invalid: false,
+            is_valid_for_best: false,
+            lap_type: LapType::Regular,
+        }
+    }
+}
+
 /// Registration result message
 ///
 /// Message Format:
@@ -213,7 +259,7 @@ pub struct LapInfo {
 /// 4   : Connection Success
 /// 5-6 : Error msg len
 /// 7-n : Error msg
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct RegistrationResult {
     pub connection_id: u32,
     pub is_readonly: bool,
@@ -225,23 +271,37 @@ pub struct RegistrationResult {
 /// 0-3 : connection id
 /// 4-5 : car count
 /// 6-n : car infos
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EntryList {
     connection_id: u32,
     pub cars: Vec<u16>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TrackData {
     connection_id: u32,
-    track_name: String,
-    track_id: u32,
-    track_meters: u32,
-    camera_sets: HashMap<String, Box<[String]>>,
-    hud_pages: Vec<String>,
+    pub track_name: String,
+    pub track_id: u32,
+    pub track_meters: u32,
+    pub camera_sets: HashMap<String, Box<[String]>>,
+    pub hud_pages: Vec<String>,
+}
+
+impl TrackData {
+    /// track data without cameras and hud pages, for stored sessions
+    pub fn stored(track_name: String, track_id: u32, track_meters: u32) -> Self {
+        TrackData {
+            connection_id: 0,
+            track_name,
+            track_id,
+            track_meters,
+            camera_sets: HashMap::new(),
+            hud_pages: vec![],
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RealtimeCarUpdate {
     pub car_index: u16,
     pub driver_index: u16,
@@ -250,7 +310,7 @@ pub struct RealtimeCarUpdate {
     pub world_pos_x: f32,
     pub world_pos_y: f32,
     pub yaw: f32,
-    pub car_location: u8, // -, track, pitlane, pit entry pit exit = 4
+    pub car_location: CarLocation,
     pub kmh: u16,
     pub position: u16,        // official P/Q/R position (1 indexed)
     pub cup_position: u16,    // official P/Q/R position (1 indexed)
@@ -263,31 +323,31 @@ pub struct RealtimeCarUpdate {
     pub current_lap: LapInfo,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RealtimeUpdate {
-    event_index: u16,
-    session_index: u16,
-    session_type: RaceSessionType,
-    phase: SessionPhase,
-    session_time: f32,     //@TODO convert into time struct?
-    session_end_time: f32, //@TODO convert into time struct?
-    focused_car_index: u32,
-    active_camera_set: String,
-    active_camera: String,
-    current_hud_page: String,
-    is_replay_playing: bool,
-    replay_session_time: Option<f32>,
-    replay_remaining_time: Option<f32>,
-    time_of_day: f32, //@TODO convert into time struct?
-    ambiant_temp: u8,
-    track_temp: u8,
-    clouds: f32,
-    rain_level: f32,
-    wetness: f32,
-    best_session_lap: LapInfo,
-}
-
-#[derive(Debug)]
+    pub event_index: u16,
+    pub session_index: u16,
+    pub session_type: RaceSessionType,
+    pub phase: SessionPhase,
+    pub session_time: f32,     //@TODO convert into time struct?
+    pub session_end_time: f32, //@TODO convert into time struct?
+    pub focused_car_index: u32,
+    pub active_camera_set: String,
+    pub active_camera: String,
+    pub current_hud_page: String,
+    pub is_replay_playing: bool,
+    pub replay_session_time: Option<f32>,
+    pub replay_remaining_time: Option<f32>,
+    pub time_of_day: f32, //@TODO convert into time struct?
+    pub ambiant_temp: u8,
+    pub track_temp: u8,
+    pub clouds: f32,
+    pub rain_level: f32,
+    pub wetness: f32,
+    pub best_session_lap: LapInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BroadcastingEvent {
     pub event_type: BroadcastingEventType,
     pub msg: String,
@@ -295,29 +355,81 @@ pub struct BroadcastingEvent {
     pub car_id: u32,
 }
 
-#[derive(Debug)]
-enum InboundMessage {
+/// Any decoded inbound message
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Inbound {
     RegistrationResult(RegistrationResult),
-    EntryList(EntryList),
-    RealtimeCarUpdate(RealtimeCarUpdate),
     RealtimeUpdate(RealtimeUpdate),
+    RealtimeCarUpdate(RealtimeCarUpdate),
+    EntryList(EntryList),
+    TrackData(TrackData),
+    EntryListCar(CarInfo),
+    BroadcastingEvent(BroadcastingEvent),
+}
+
+impl Inbound {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Inbound::RegistrationResult(_) => "registration-result",
+            Inbound::RealtimeUpdate(_) => "realtime-update",
+            Inbound::RealtimeCarUpdate(_) => "realtime-car-update",
+            Inbound::EntryList(_) => "entry-list",
+            Inbound::TrackData(_) => "track-data",
+            Inbound::EntryListCar(_) => "entry-list-car",
+            Inbound::BroadcastingEvent(_) => "broadcasting-event",
+        }
+    }
+
+    /// the car a message is about, if any
+    pub fn car_index(&self) -> Option<u16> {
+        match self {
+            Inbound::RealtimeCarUpdate(update) => Some(update.car_index),
+            Inbound::EntryListCar(car_info) => Some(car_info.car_index),
+            Inbound::BroadcastingEvent(event) => u16::try_from(event.car_id).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// One decoded field of a packet, recorded when field tracing is on
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    /// byte offset in the packet
+    pub offset: usize,
+    pub len: usize,
+    /// field name, prefixed with the lap it belongs to for lap fields
+    pub name: String,
+    pub kind: &'static str,
+    pub value: String,
 }
 
 pub struct UdpReader {
     buf: [u8; 65507],
     size: usize,
     pointer: usize,
+    /// fields decoded from the current packet, `None` when not tracing
+    fields: Option<Vec<Field>>,
+    /// lap the fields being read belong to
+    group: Option<&'static str>,
     pub socket: UdpSocket,
 }
 
 impl UdpReader {
     pub fn new() -> Self {
-        UdpReader {
+        Self::bind().expect("unable to bind to UDP socket")
+    }
+
+    pub fn bind() -> Result<Self, String> {
+        Ok(UdpReader {
             buf: [0; 65507],
             size: 0,
             pointer: 0,
-            socket: UdpSocket::bind("127.0.0.1:0").expect("unable to bind to UDP socket"),
-        }
+            fields: None,
+            group: None,
+            socket: UdpSocket::bind("127.0.0.1:0")
+                .map_err(|e| format!("unable to bind to UDP socket: {}", e))?,
+        })
     }
 
     /// Listens for new UDP data
@@ -328,63 +440,170 @@ impl UdpReader {
         self.size = self
             .socket
             .recv(&mut self.buf)
-            .expect("could not read socket");
-        self.pointer = 0;
+            .map_err(|e| format!("could not read socket: {}", e))?;
+        self.reset();
         trace!("reader read: {:?}", self.size);
         Ok(self.size)
     }
 
-    fn read_bytes(&mut self, count: usize) -> Result<Vec<u8>, String> {
-        Ok(self.buf[self.pointer..self.pointer + count].to_vec()).and_then(|result| {
-            self.pointer += count;
-            Ok(result)
-        })
+    /// puts a stored packet into the buffer as if it had been received
+    pub fn load(&mut self, packet: &[u8]) -> Result<(), String> {
+        let Some(buf) = self.buf.get_mut(..packet.len()) else {
+            return Err(format!("packet of {} bytes is too large", packet.len()));
+        };
+        buf.copy_from_slice(packet);
+        self.size = packet.len();
+        self.reset();
+        Ok(())
     }
 
-    fn read_string(&mut self) -> Result<String, String> {
-        let size = u16::from_le_bytes(self.read_bytes(2).unwrap().try_into().unwrap());
-        match core::str::from_utf8(&self.read_bytes(size as usize).unwrap()) {
-            Ok(s) => Ok(s.to_owned()),
-            Err(_e) => {
-                error!("buf pointer: {}", self.pointer);
-                let mut f = File::create("dump.dat").unwrap();
-                f.write_all(&self.buf).unwrap();
-                disconnect(&self.socket).unwrap();
-                Err("could not parse string".to_string())
-            }
+    fn reset(&mut self) {
+        self.pointer = 0;
+        self.group = None;
+        if let Some(fields) = &mut self.fields {
+            fields.clear();
+        }
+    }
+
+    /// the packet received by the last `listen`
+    pub fn packet(&self) -> &[u8] {
+        &self.buf[..self.size]
+    }
+
+    /// offset of the next byte to be read, where decoding stopped on failure
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    /// records every decoded field from now on, see `fields`
+    pub fn trace_fields(&mut self, enabled: bool) {
+        self.fields = enabled.then(Vec::new);
+    }
+
+    /// the fields decoded from the current packet so far
+    pub fn fields(&self) -> &[Field] {
+        self.fields.as_deref().unwrap_or_default()
+    }
+
+    fn read_bytes(&mut self, name: &'static str, count: usize) -> Result<&[u8], String> {
+        let start = self.pointer;
+        let end = start
+            .checked_add(count)
+            .filter(|end| *end <= self.size)
+            .ok_or_else(|| {
+                format!(
+                    "{} needs {} bytes at offset {} but the packet has {}",
+                    self.field_name(name),
+                    count,
+                    start,
+                    self.size
+                )
+            })?;
+        self.pointer = end;
+        Ok(&self.buf[start..end])
+    }
+
+    /// fails unless `count` items of at least `size` bytes each are left,
+    /// so a corrupt count cannot make the parser allocate or loop for long
+    fn check_count(&self, name: &'static str, count: usize, size: usize) -> Result<(), String> {
+        let left = self.size.saturating_sub(self.pointer);
+        if count.saturating_mul(size) > left {
+            return Err(format!(
+                "{} of {} needs at least {} bytes at offset {} but only {} are left",
+                self.field_name(name),
+                count,
+                count.saturating_mul(size),
+                self.pointer,
+                left
+            ));
+        }
+        Ok(())
+    }
+
+    fn field_name(&self, name: &str) -> String {
+        match self.group {
+            Some(group) => format!("{}.{}", group, name),
+            None => name.to_string(),
+        }
+    }
+
+    fn note(&mut self, name: &'static str, kind: &'static str, offset: usize, value: String) {
+        if self.fields.is_none() {
+            return;
+        }
+        let field = Field {
+            offset,
+            len: self.pointer - offset,
+            name: self.field_name(name),
+            kind,
+            value,
+        };
+        if let Some(fields) = &mut self.fields {
+            fields.push(field);
         }
     }
 
-    fn read_u32(&mut self) -> Result<u32, String> {
-        Ok(u32::from_le_bytes(
-            self.read_bytes(4).unwrap().try_into().unwrap(),
-        ))
+    fn read_array<const N: usize>(&mut self, name: &'static str) -> Result<[u8; N], String> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.read_bytes(name, N)?);
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self, name: &'static str) -> Result<String, String> {
+        let offset = self.pointer;
+        let size = u16::from_le_bytes(self.read_array(name)?);
+        let bytes = self.read_bytes(name, size as usize)?;
+        let value = match core::str::from_utf8(bytes) {
+            Ok(value) => value.to_owned(),
+            Err(e) => {
+                // point at the string for the inspector hexdump
+                self.pointer = offset;
+                return Err(format!(
+                    "{} at offset {} is not valid utf-8: {}",
+                    self.field_name(name),
+                    offset,
+                    e
+                ));
+            }
+        };
+        self.note(name, "string", offset, format!("{:?}", value));
+        Ok(value)
+    }
+
+    fn read_u32(&mut self, name: &'static str) -> Result<u32, String> {
+        let offset = self.pointer;
+        let value = u32::from_le_bytes(self.read_array(name)?);
+        self.note(name, "u32", offset, value.to_string());
+        Ok(value)
     }
 
-    fn read_u16(&mut self) -> Result<u16, String> {
-        Ok(u16::from_le_bytes(
-            self.read_bytes(2).unwrap().try_into().unwrap(),
-        ))
+    fn read_u16(&mut self, name: &'static str) -> Result<u16, String> {
+        let offset = self.pointer;
+        let value = u16::from_le_bytes(self.read_array(name)?);
+        self.note(name, "u16", offset, value.to_string());
+        Ok(value)
     }
 
-    pub fn read_u8(&mut self) -> Result<u8, String> {
-        Ok(u8::from_le_bytes(
-            self.read_bytes(1).unwrap().try_into().unwrap(),
-        ))
+    fn read_u8(&mut self, name: &'static str) -> Result<u8, String> {
+        let offset = self.pointer;
+        let value = u8::from_le_bytes(self.read_array(name)?);
+        self.note(name, "u8", offset, value.to_string());
+        Ok(value)
     }
 
-    fn read_f32(&mut self) -> Result<f32, String> {
-        Ok(f32::from_le_bytes(
-            self.read_bytes(4).unwrap().try_into().unwrap(),
-        ))
+    fn read_f32(&mut self, name: &'static str) -> Result<f32, String> {
+        let offset = self.pointer;
+        let value = f32::from_le_bytes(self.read_array(name)?);
+        self.note(name, "f32", offset, value.to_string());
+        Ok(value)
     }
 }
 
 pub fn connect(socket: &UdpSocket, addr: SocketAddr) -> Result<usize, Error> {
-    socket.connect(addr).unwrap();
+    socket.connect(addr)?;
     let mut buf = Vec::with_capacity(26);
     buf.push(OutboundMessageType::RegisterCommand as u8);
-    buf.push(BROADCASTING_PROTOCOL_VERSION as u8);
+    buf.push(BROADCASTING_PROTOCOL_VERSION);
     buf.extend_from_slice(&4u16.to_le_bytes());
     buf.extend_from_slice(b"name"); // display name
     buf.extend_from_slice(&3u16.to_le_bytes());
@@ -417,33 +636,62 @@ pub fn request_track_data(socket: &UdpSocket, connection_id: u32) -> Result<usiz
     socket.send(&buf)
 }
 
+/// decodes the packet in the buffer, whatever type it is
+pub fn parse(reader: &mut UdpReader) -> Result<Inbound, String> {
+    Ok(
+        match InboundMessageType::try_from(reader.read_u8("message_type")?)? {
+            InboundMessageType::RegistrationResult => {
+                Inbound::RegistrationResult(parse_registration_result(reader)?)
+            }
+            InboundMessageType::RealtimeUpdate => {
+                Inbound::RealtimeUpdate(parse_realtime_update(reader)?)
+            }
+            InboundMessageType::RealtimeCarUpdate => {
+                Inbound::RealtimeCarUpdate(parse_realtime_car_update(reader)?)
+            }
+            InboundMessageType::EntryList => Inbound::EntryList(parse_entry_list(reader)?),
+            InboundMessageType::TrackData => Inbound::TrackData(parse_track_data(reader)?),
+            InboundMessageType::EntryListCar => {
+                Inbound::EntryListCar(parse_entry_list_car(reader)?)
+            }
+            InboundMessageType::BroadcastingEvent => {
+                Inbound::BroadcastingEvent(parse_broadcasting_event(reader)?)
+            }
+        },
+    )
+}
+
 pub fn parse_registration_result(reader: &mut UdpReader) -> Result<RegistrationResult, String> {
-    let connection_id = reader.read_u32().unwrap();
-    if reader.read_u8().unwrap() > 0 {
+    let connection_id = reader.read_u32("connection_id")?;
+    if reader.read_u8("success")? > 0 {
         Ok(RegistrationResult {
-            connection_id: connection_id,
-            is_readonly: reader.read_u8().unwrap() == 0,
+            connection_id,
+            is_readonly: reader.read_u8("is_readonly")? == 0,
         })
     } else {
-        reader.read_u8().unwrap();
-        Err(reader.read_string().unwrap())
+        reader.read_u8("is_readonly")?;
+        Err(reader.read_string("error_message")?)
     }
 }
 
-fn parse_lap(reader: &mut UdpReader) -> Result<LapInfo, String> {
-    let laptime_ms = reader.read_u32().unwrap();
-    let car_index = reader.read_u16().unwrap();
-    let driver_index = reader.read_u16().unwrap();
+/// `name` is the lap the traced fields are reported under, e.g. `last_lap`
+pub fn parse_lap(reader: &mut UdpReader, name: &'static str) -> Result<LapInfo, String> {
+    reader.group = Some(name);
+    let laptime_ms = reader.read_u32("laptime_ms")?;
+    let car_index = reader.read_u16("car_index")?;
+    let driver_index = reader.read_u16("driver_index")?;
 
-    let split_count = reader.read_u8().unwrap();
-    let mut splits: Vec<u32> = vec![];
+    let split_count = reader.read_u8("split_count")?;
+    reader.check_count("split_count", split_count.into(), 4)?;
+    let mut splits: Vec<u32> = Vec::with_capacity(split_count.into());
     for _i in 0..split_count {
-        splits.push(reader.read_u32().unwrap());
+        splits.push(reader.read_u32("split")?);
     }
-    let is_invalid = reader.read_u8().unwrap() > 0;
-    let is_valid_for_best = reader.read_u8().unwrap() > 0;
-    let is_outlap = reader.read_u8().unwrap() > 0;
-    let is_inlap = reader.read_u8().unwrap() > 0;
+    let is_invalid = reader.read_u8("is_invalid")? > 0;
+    let is_valid_for_best = reader.read_u8("is_valid_for_best")? > 0;
+    let is_outlap = reader.read_u8("is_outlap")? > 0;
+    let is_inlap = reader.read_u8("is_inlap")? > 0;
+    reader.group = None;
 
     let lap_type = if is_outlap {
         LapType::Outlap
@@ -470,31 +718,31 @@ fn parse_lap(reader: &mut UdpReader) -> Result<LapInfo, String> {
 }
 
 pub fn parse_realtime_update(reader: &mut UdpReader) -> Result<RealtimeUpdate, String> {
-    let event_index = reader.read_u16().unwrap();
-    let session_index = reader.read_u16().unwrap();
-    let session_type = RaceSessionType::try_from(reader.read_u8().unwrap()).unwrap();
-    let phase = SessionPhase::try_from(reader.read_u8().unwrap()).unwrap();
-    let session_time = reader.read_f32().unwrap();
-    let session_end_time = reader.read_f32().unwrap();
-    let focused_car_index = reader.read_u32().unwrap();
-    let active_camera_set = reader.read_string().unwrap();
-    let active_camera = reader.read_string().unwrap();
-    let current_hud_page = reader.read_string().unwrap();
-    let is_replay_playing = reader.read_u8().unwrap() > 0;
+    let event_index = reader.read_u16("event_index")?;
+    let session_index = reader.read_u16("session_index")?;
+    let session_type = RaceSessionType::try_from(reader.read_u8("session_type")?)?;
+    let phase = SessionPhase::try_from(reader.read_u8("phase")?)?;
+    let session_time = reader.read_f32("session_time")?;
+    let session_end_time = reader.read_f32("session_end_time")?;
+    let focused_car_index = reader.read_u32("focused_car_index")?;
+    let active_camera_set = reader.read_string("active_camera_set")?;
+    let active_camera = reader.read_string("active_camera")?;
+    let current_hud_page = reader.read_string("current_hud_page")?;
+    let is_replay_playing = reader.read_u8("is_replay_playing")? > 0;
     let mut replay_session_time: Option<f32> = None;
     let mut replay_remaining_time: Option<f32> = None;
     if is_replay_playing {
-        replay_session_time = Some(reader.read_f32().unwrap());
-        replay_remaining_time = Some(reader.read_f32().unwrap());
+        replay_session_time = Some(reader.read_f32("replay_session_time")?);
+        replay_remaining_time = Some(reader.read_f32("replay_remaining_time")?);
     }
 
-    let time_of_day = reader.read_f32().unwrap();
-    let ambiant_temp = reader.read_u8().unwrap();
-    let track_temp = reader.read_u8().unwrap();
-    let clouds = reader.read_u8().unwrap() as f32 / 10.0f32;
-    let rain_level = reader.read_u8().unwrap() as f32 / 10.0f32;
-    let wetness = reader.read_u8().unwrap() as f32 / 10.0f32;
-    let best_session_lap = parse_lap(reader).unwrap();
+    let time_of_day = reader.read_f32("time_of_day")?;
+    let ambiant_temp = reader.read_u8("ambiant_temp")?;
+    let track_temp = reader.read_u8("track_temp")?;
+    let clouds = reader.read_u8("clouds")? as f32 / 10.0f32;
+    let rain_level = reader.read_u8("rain_level")? as f32 / 10.0f32;
+    let wetness = reader.read_u8("wetness")? as f32 / 10.0f32;
+    let best_session_lap = parse_lap(reader, "best_session_lap")?;
 
     Ok(RealtimeUpdate {
         event_index,
@@ -521,24 +769,24 @@ pub fn parse_realtime_update(reader: &mut UdpReader) -> Result<RealtimeUpdate, S
 }
 
 pub fn parse_realtime_car_update(reader: &mut UdpReader) -> Result<RealtimeCarUpdate, String> {
-    let car_index = reader.read_u16().unwrap();
-    let driver_index = reader.read_u16().unwrap();
-    let driver_count = reader.read_u8().unwrap();
-    let gear = reader.read_u8().unwrap();
-    let world_x = reader.read_f32().unwrap();
-    let world_y = reader.read_f32().unwrap();
-    let yaw = reader.read_f32().unwrap();
-    let car_location = reader.read_u8().unwrap();
-    let kmh = reader.read_u16().unwrap();
-    let position = reader.read_u16().unwrap();
-    let cup_position = reader.read_u16().unwrap();
-    let track_position = reader.read_u16().unwrap();
-    let spline_position = reader.read_f32().unwrap();
-    let laps = reader.read_u16().unwrap();
-    let delta = reader.read_u32().unwrap();
-    let best_session_lap = parse_lap(reader).unwrap();
-    let last_lap = parse_lap(reader).unwrap();
-    let current_lap = parse_lap(reader).unwrap();
+    let car_index = reader.read_u16("car_index")?;
+    let driver_index = reader.read_u16("driver_index")?;
+    let driver_count = reader.read_u8("driver_count")?;
+    let gear = reader.read_u8("gear")?;
+    let world_x = reader.read_f32("world_x")?;
+    let world_y = reader.read_f32("world_y")?;
+    let yaw = reader.read_f32("yaw")?;
+    let car_location = CarLocation::try_from(reader.read_u8("car_location")?)?;
+    let kmh = reader.read_u16("kmh")?;
+    let position = reader.read_u16("position")?;
+    let cup_position = reader.read_u16("cup_position")?;
+    let track_position = reader.read_u16("track_position")?;
+    let spline_position = reader.read_f32("spline_position")?;
+    let laps = reader.read_u16("laps")?;
+    let delta = reader.read_u32("delta")?;
+    let best_session_lap = parse_lap(reader, "best_session_lap")?;
+    let last_lap = parse_lap(reader, "last_lap")?;
+    let current_lap = parse_lap(reader, "current_lap")?;
 
     Ok(RealtimeCarUpdate {
         car_index,
@@ -563,15 +811,16 @@ pub fn parse_realtime_car_update(reader: &mut UdpReader) -> Result<RealtimeCarUp
 }
 
 pub fn parse_entry_list(reader: &mut UdpReader) -> Result<EntryList, String> {
-    let connection_id = reader.read_u32().unwrap();
-    let car_count = reader.read_u16().unwrap();
+    let connection_id = reader.read_u32("connection_id")?;
+    let car_count = reader.read_u16("car_count")?;
+    reader.check_count("car_count", car_count.into(), 2)?;
     let mut entries = EntryList {
-        connection_id: connection_id,
+        connection_id,
         cars: vec![],
     };
 
     for _i in 0..car_count {
-        let index = u16::from_le_bytes(reader.read_bytes(2).unwrap().try_into().unwrap());
+        let index = reader.read_u16("car_index")?;
         entries.cars.push(index);
     }
 
@@ -579,22 +828,24 @@ pub fn parse_entry_list(reader: &mut UdpReader) -> Result<EntryList, String> {
 }
 
 pub fn parse_entry_list_car(reader: &mut UdpReader) -> Result<CarInfo, String> {
-    let car_index = reader.read_u16().unwrap();
-    let car_model_type = reader.read_u8().unwrap();
-    let team_name = reader.read_string().unwrap();
-    let race_number = reader.read_u32().unwrap();
-    let cup_category = reader.read_u8().unwrap();
-    let current_driver_index = reader.read_u8().unwrap();
-    let nationality = reader.read_u16().unwrap();
-
-    let driver_count = reader.read_u8().unwrap();
+    let car_index = reader.read_u16("car_index")?;
+    let car_model_type = reader.read_u8("car_model_type")?;
+    let team_name = reader.read_string("team_name")?;
+    let race_number = reader.read_u32("race_number")?;
+    let cup_category = reader.read_u8("cup_category")?;
+    let current_driver_index = reader.read_u8("current_driver_index")?;
+    let nationality = reader.read_u16("nationality")?;
+
+    let driver_count = reader.read_u8("driver_count")?;
+    // three empty names, category and nationality
+    reader.check_count("driver_count", driver_count.into(), 9)?;
     let mut drivers = Vec::with_capacity(driver_count.into());
     for _i in 0..driver_count {
-        let first_name = reader.read_string().unwrap();
-        let last_name = reader.read_string().unwrap();
-        let short_name = reader.read_string().unwrap();
-        let category = reader.read_u8().unwrap();
-        let nationality = reader.read_u16().unwrap();
+        let first_name = reader.read_string("first_name")?;
+        let last_name = reader.read_string("last_name")?;
+        let short_name = reader.read_string("short_name")?;
+        let category = reader.read_u8("category")?;
+        let nationality = reader.read_u16("nationality")?;
 
         drivers.push(DriverInfo {
             first_name,
@@ -618,45 +869,49 @@ pub fn parse_entry_list_car(reader: &mut UdpReader) -> Result<CarInfo, String> {
 }
 
 pub fn parse_track_data(reader: &mut UdpReader) -> Result<TrackData, String> {
-    let connection_id = reader.read_u32().unwrap();
-    let track_name = reader.read_string().unwrap();
-    let track_id = reader.read_u32().unwrap();
-    let track_meters = reader.read_u32().unwrap();
+    let connection_id = reader.read_u32("connection_id")?;
+    let track_name = reader.read_string("track_name")?;
+    let track_id = reader.read_u32("track_id")?;
+    let track_meters = reader.read_u32("track_meters")?;
     let mut camera_sets = HashMap::new();
-    let camera_set_count = reader.read_u8().unwrap();
+    let camera_set_count = reader.read_u8("camera_set_count")?;
+    // an empty name and camera count
+    reader.check_count("camera_set_count", camera_set_count.into(), 3)?;
     for _i in 0..camera_set_count {
-        let camera_set_name = reader.read_string().unwrap();
-        let camera_count = reader.read_u8().unwrap();
+        let camera_set_name = reader.read_string("camera_set_name")?;
+        let camera_count = reader.read_u8("camera_count")?;
+        reader.check_count("camera_count", camera_count.into(), 2)?;
 
         let mut camera_set = Vec::with_capacity(camera_count.into());
         for _j in 0..camera_count {
-            camera_set.push(reader.read_string().unwrap());
+            camera_set.push(reader.read_string("camera")?);
         }
 
         camera_sets.insert(camera_set_name.clone(), camera_set.as_slice().into());
     }
 
-    let hud_pages_count = reader.read_u8().unwrap();
+    let hud_pages_count = reader.read_u8("hud_pages_count")?;
+    reader.check_count("hud_pages_count", hud_pages_count.into(), 2)?;
     let mut hud_pages: Vec<String> = Vec::with_capacity(hud_pages_count.into());
 
     for _i in 0..hud_pages_count {
-        hud_pages.push(reader.read_string().unwrap());
+        hud_pages.push(reader.read_string("hud_page")?);
     }
     Ok(TrackData {
-        connection_id: connection_id,
-        track_name: track_name,
-        track_id: track_id,
-        track_meters: track_meters,
-        camera_sets: camera_sets,
-        hud_pages: hud_pages,
+        connection_id,
+        track_name,
+        track_id,
+        track_meters,
+        camera_sets,
+        hud_pages,
     })
 }
 
 pub fn parse_broadcasting_event(reader: &mut UdpReader) -> Result<BroadcastingEvent, String> {
-    let event_type = BroadcastingEventType::try_from(reader.read_u8().unwrap()).unwrap();
-    let msg = reader.read_string().unwrap();
-    let time_ms = reader.read_u32().unwrap();
-    let car_id = reader.read_u32().unwrap();
+    let event_type = BroadcastingEventType::try_from(reader.read_u8("event_type")?)?;
+    let msg = reader.read_string("msg")?;
+    let time_ms = reader.read_u32("time_ms")?;
+    let car_id = reader.read_u32("car_id")?;
 
     Ok(BroadcastingEvent {
         event_type,
@@ -665,3 +920,33 @@ pub fn parse_broadcasting_event(reader: &mut UdpReader) -> Result<BroadcastingEv
         car_id,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let packets = fixture::short_race();
+        let mut seen = HashSet::new();
+        let mut reader = UdpReader::new();
+        // one packet of every message type the race sends
+        for packet in packets.iter().filter(|packet| seen.insert(packet.bytes[0])) {
+            reader.load(&packet.bytes).unwrap();
+            assert!(parse(&mut reader).is_ok(), "type {}", packet.bytes[0]);
+            for len in 0..packet.bytes.len() {
+                reader.load(&packet.bytes[..len]).unwrap();
+                assert!(
+                    parse(&mut reader).is_err(),
+                    "type {} cut to {} bytes",
+                    packet.bytes[0],
+                    len
+                );
+            }
+        }
+        assert_eq!(seen.len(), 7);
+    }
+}
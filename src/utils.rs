@@ -1,6 +1,15 @@
+use std::path::PathBuf;
+
 pub fn ms_to_string(ms: u32) -> String {
     let min = ms / 60_000;
     let sec = (ms - (60_000 * min)) / 1000;
     let rest = ms - (60_000 * min) - (1000 * sec);
     format!("{:?}:{:?}.{:?}", min, sec, rest)
 }
+
+/// directory backmarker keeps its files in
+pub fn data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("backmarker")
+}
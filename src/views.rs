@@ -0,0 +1,25 @@
+//! Module for iced views
+//!
+//! Each submodule renders one board from the session model.
+
+pub mod alerts;
+pub mod charts;
+pub mod head_to_head;
+pub mod pace;
+pub mod positions;
+pub mod race_control;
+pub mod relative;
+pub mod sessions;
+pub mod standings;
+pub mod track_map;
+pub mod tyres;
+pub mod weather;
+
+use iced::Color;
+
+use crate::car_models::CarClass;
+
+pub fn class_color(class: CarClass) -> Color {
+    let (r, g, b) = class.rgb();
+    Color::from_rgb(r, g, b)
+}
@@ -0,0 +1,26 @@
+use iced::{
+    widget::{row, text, Column},
+    Color, Element,
+};
+
+use crate::{alerts::Severity, session::Session, utils, Message};
+
+const WARNING_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+/// alerts shown on the board, newest first
+const SHOWN: usize = 8;
+
+pub fn view(session: &Session) -> Element<'_, Message> {
+    Column::with_children(session.alerts.iter().rev().take(SHOWN).map(|alert| {
+        let color = match alert.severity {
+            Severity::Info => None,
+            Severity::Warning => Some(WARNING_COLOR),
+        };
+        row![
+            text(utils::ms_to_string(alert.session_time as u32)),
+            text(&alert.text).color_maybe(color)
+        ]
+        .spacing(6)
+        .into()
+    }))
+    .into()
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+
+use iced::{
+    alignment, mouse,
+    widget::{
+        button,
+        canvas::{self, Frame, Geometry, Path, Stroke, Text},
+        column, row, text, Canvas, Row,
+    },
+    Color, Element,
+    Length::Fill,
+    Point, Rectangle, Renderer, Size, Theme, Vector,
+};
+
+use crate::{car_models::CarClass, classification, session::Session, views::class_color, Message};
+
+const MARGIN: f32 = 40.0;
+const AXIS_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
+const TOOLTIP_BACKGROUND: Color = Color::from_rgba(0.1, 0.1, 0.1, 0.9);
+/// cursor distance in pixels a point is picked up for the tooltip from
+const HOVER_DISTANCE: f32 = 10.0;
+
+/// One car's line on a chart
+pub struct Series {
+    pub color: Color,
+    pub focused: bool,
+    /// draw the points on their own instead of joining them
+    pub dots: bool,
+    /// (x, y, tooltip) in chart units
+    pub points: Vec<(f32, f32, String)>,
+}
+
+/// Line chart with the best value (P1, no gap, fastest lap) at the top
+pub struct LineChart {
+    pub series: Vec<Series>,
+    pub x_label: fn(f32) -> String,
+    pub y_label: fn(f32) -> String,
+}
+
+impl LineChart {
+    fn range(&self, value: impl Fn(&(f32, f32, String)) -> f32) -> Option<(f32, f32)> {
+        let values = self
+            .series
+            .iter()
+            .flat_map(|series| series.points.iter().map(&value));
+        let low = values.clone().reduce(f32::min)?;
+        let high = values.reduce(f32::max)?;
+        Some((low, if high > low { high } else { low + 1.0 }))
+    }
+}
+
+impl canvas::Program<Message> for LineChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let (Some((x_low, x_high)), Some((y_low, y_high))) =
+            (self.range(|p| p.0), self.range(|p| p.1))
+        else {
+            return vec![frame.into_geometry()];
+        };
+        let width = (bounds.width - 2.0 * MARGIN).max(1.0);
+        let height = (bounds.height - 2.0 * MARGIN).max(1.0);
+        let project = |x: f32, y: f32| {
+            Point::new(
+                MARGIN + (x - x_low) / (x_high - x_low) * width,
+                MARGIN + (y - y_low) / (y_high - y_low) * height,
+            )
+        };
+
+        frame.stroke(
+            &Path::rectangle(Point::new(MARGIN, MARGIN), Size::new(width, height)),
+            Stroke::default().with_color(AXIS_COLOR),
+        );
+        for (content, position, horizontal) in [
+            (
+                (self.y_label)(y_low),
+                Point::new(MARGIN - 4.0, MARGIN),
+                alignment::Horizontal::Right,
+            ),
+            (
+                (self.y_label)(y_high),
+                Point::new(MARGIN - 4.0, MARGIN + height),
+                alignment::Horizontal::Right,
+            ),
+            (
+                (self.x_label)(x_low),
+                Point::new(MARGIN, MARGIN + height + 4.0),
+                alignment::Horizontal::Left,
+            ),
+            (
+                (self.x_label)(x_high),
+                Point::new(MARGIN + width, MARGIN + height + 4.0),
+                alignment::Horizontal::Right,
+            ),
+        ] {
+            frame.fill_text(Text {
+                content,
+                position,
+                color: AXIS_COLOR,
+                horizontal_alignment: horizontal,
+                ..Text::default()
+            });
+        }
+
+        // our car last so it is drawn on top
+        let mut series: Vec<&Series> = self.series.iter().collect();
+        series.sort_by_key(|series| series.focused);
+        for series in &series {
+            if series.dots {
+                for (x, y, _) in &series.points {
+                    frame.fill(&Path::circle(project(*x, *y), 3.0), series.color);
+                }
+                continue;
+            }
+            let path = Path::new(|builder| {
+                for (i, (x, y, _)) in series.points.iter().enumerate() {
+                    if i == 0 {
+                        builder.move_to(project(*x, *y));
+                    } else {
+                        builder.line_to(project(*x, *y));
+                    }
+                }
+            });
+            let width = if series.focused { 4.0 } else { 1.5 };
+            frame.stroke(
+                &path,
+                Stroke::default().with_color(series.color).with_width(width),
+            );
+        }
+
+        let hovered = cursor.position_in(bounds).and_then(|cursor| {
+            series
+                .iter()
+                .flat_map(|series| series.points.iter())
+                .map(|(x, y, tooltip)| (project(*x, *y), tooltip))
+                .map(|(point, tooltip)| (point.distance(cursor), point, tooltip))
+                .filter(|(distance, _, _)| *distance <= HOVER_DISTANCE)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+        });
+        if let Some((_, point, tooltip)) = hovered {
+            frame.fill(&Path::circle(point, 4.0), Color::WHITE);
+            let size = Size::new(tooltip.len() as f32 * 8.0 + 8.0, 20.0);
+            let corner = Point::new(
+                (point.x + 8.0).min(bounds.width - size.width),
+                (point.y - size.height - 4.0).max(0.0),
+            );
+            frame.fill_rectangle(corner, size, TOOLTIP_BACKGROUND);
+            frame.fill_text(Text {
+                content: tooltip.clone(),
+                position: corner + Vector::new(4.0, 3.0),
+                color: Color::WHITE,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// position of every shown car at the end of every lap, ranked among the
+/// shown cars so a class filter gives class positions
+fn lap_chart(session: &Session, filter: Option<CarClass>) -> LineChart {
+    let focused = session.focused_car();
+    let cars: Vec<_> = session
+        .cars
+        .values()
+        .filter(|car| filter.is_none_or(|class| car.class() == class))
+        .collect();
+
+    let mut by_lap: BTreeMap<u16, Vec<(u16, u16)>> = BTreeMap::new();
+    for car in &cars {
+        for lap in car.laps.iter().filter(|lap| lap.position > 0) {
+            by_lap
+                .entry(lap.number)
+                .or_default()
+                .push((lap.position, car.car_info.car_index));
+        }
+    }
+    let mut ranks: BTreeMap<u16, Vec<(f32, f32, String)>> = BTreeMap::new();
+    for (number, mut placings) in by_lap {
+        placings.sort();
+        for (rank, (_, index)) in placings.into_iter().enumerate() {
+            ranks
+                .entry(index)
+                .or_default()
+                .push((number as f32, rank as f32 + 1.0, String::new()));
+        }
+    }
+
+    let series = cars
+        .iter()
+        .filter_map(|car| {
+            let mut points = ranks.remove(&car.car_info.car_index)?;
+            for (lap, position, tooltip) in points.iter_mut() {
+                *tooltip = format!("#{} L{} P{}", car.car_info.race_number, lap, position);
+            }
+            Some(Series {
+                color: class_color(car.class()),
+                focused: focused == Some(car.car_info.car_index),
+                dots: false,
+                points,
+            })
+        })
+        .collect();
+    LineChart {
+        series,
+        x_label: |lap| format!("L{:.0}", lap),
+        y_label: |position| format!("P{:.0}", position),
+    }
+}
+
+/// gap of every shown car to the first shown car over the line on each lap
+fn gap_chart(session: &Session, filter: Option<CarClass>) -> LineChart {
+    let focused = session.focused_car();
+    let cars: Vec<_> = session
+        .cars
+        .values()
+        .filter(|car| filter.is_none_or(|class| car.class() == class))
+        .collect();
+
+    let mut leader_times: BTreeMap<u16, f32> = BTreeMap::new();
+    for lap in cars.iter().flat_map(|car| car.laps.iter()) {
+        let time = leader_times.entry(lap.number).or_insert(lap.completed_at);
+        *time = time.min(lap.completed_at);
+    }
+
+    let series = cars
+        .iter()
+        .filter(|car| !car.laps.is_empty())
+        .map(|car| Series {
+            color: class_color(car.class()),
+            focused: focused == Some(car.car_info.car_index),
+            dots: false,
+            points: car
+                .laps
+                .iter()
+                .map(|lap| {
+                    let gap = (lap.completed_at - leader_times[&lap.number]) / 1000.0;
+                    (
+                        lap.completed_at,
+                        gap,
+                        format!("#{} L{} +{:.1}s", car.car_info.race_number, lap.number, gap),
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+    LineChart {
+        series,
+        x_label: |time| format!("{:.0} min", time / 60_000.0),
+        y_label: |gap| format!("+{:.0}s", gap),
+    }
+}
+
+pub fn view(session: &Session, filter: Option<CarClass>) -> Element<'_, Message> {
+    let filters = Row::with_children(
+        std::iter::once(button("all").on_press(Message::ChartFilter(None)).into()).chain(
+            classification::classes(session).into_iter().map(|class| {
+                button(text(class.name()).color(class_color(class)))
+                    .on_press(Message::ChartFilter(Some(class)))
+                    .into()
+            }),
+        ),
+    )
+    .spacing(4);
+
+    column![
+        row![text("lap chart"), filters].spacing(10),
+        Canvas::new(lap_chart(session, filter))
+            .width(Fill)
+            .height(Fill),
+        text("gap to leader"),
+        Canvas::new(gap_chart(session, filter))
+            .width(Fill)
+            .height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
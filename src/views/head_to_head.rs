@@ -0,0 +1,145 @@
+use iced::{
+    widget::{button, column, row, scrollable, text, Column, Row},
+    Color, Element,
+    Length::Fill,
+};
+
+use crate::{
+    head_to_head::{self, Stronger, SECTORS},
+    session::{Car, Session},
+    views::class_color,
+    Message,
+};
+
+const GAIN_COLOR: Color = Color::from_rgb(0.3, 0.8, 0.3);
+const LOSS_COLOR: Color = Color::from_rgb(0.9, 0.3, 0.3);
+
+/// Which side of the comparison a picked car goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    A,
+    B,
+}
+
+fn sector_time(ms: Option<u32>) -> String {
+    ms.map_or(String::from("-"), |ms| format!("{:.3}", ms as f32 / 1000.0))
+}
+
+/// delta in seconds, colored for `b`
+fn delta(ms: Option<i64>) -> Element<'static, Message> {
+    match ms {
+        Some(ms) => {
+            let color = if ms < 0 { GAIN_COLOR } else { LOSS_COLOR };
+            text(format!("{:+.3}", ms as f32 / 1000.0))
+                .color(color)
+                .into()
+        }
+        None => text("-").into(),
+    }
+}
+
+fn picker(session: &Session, side: Side, picked: Option<u16>) -> Element<'_, Message> {
+    let label = match side {
+        Side::A => "A:",
+        Side::B => "B:",
+    };
+    Row::with_children(std::iter::once(text(label).into()).chain(
+        session.standings().into_iter().map(|car| {
+            let index = car.car_info.car_index;
+            let number = if picked == Some(index) {
+                format!("[#{}]", car.car_info.race_number)
+            } else {
+                format!("#{}", car.car_info.race_number)
+            };
+            button(text(number).color(class_color(car.class())))
+                .on_press(Message::HeadToHead(side, index))
+                .into()
+        }),
+    ))
+    .spacing(4)
+    .wrap()
+    .into()
+}
+
+fn comparison<'a>(a: &Car, b: &Car) -> Element<'a, Message> {
+    let comparison = head_to_head::compare(a, b);
+    let (number_a, number_b) = (a.car_info.race_number, b.car_info.race_number);
+
+    let header =
+        Row::with_children(std::iter::once(text("lap").into()).chain((1..=SECTORS).map(
+            |sector| text(format!("S{} #{} / #{} / delta", sector, number_a, number_b)).into(),
+        )))
+        .spacing(20);
+    let laps = comparison.laps.iter().rev().map(|pair| {
+        Row::with_children(
+            std::iter::once(text(format!("L{}", pair.number)).into()).chain((0..SECTORS).map(
+                |sector| {
+                    row![
+                        text(sector_time(pair.a[sector])),
+                        text(sector_time(pair.b[sector])),
+                        delta(pair.delta(sector))
+                    ]
+                    .spacing(6)
+                    .into()
+                },
+            )),
+        )
+        .spacing(20)
+        .into()
+    });
+
+    let stint = Row::with_children(
+        std::iter::once(text("stint").into()).chain(
+            comparison
+                .stint_delta
+                .iter()
+                .map(|total| delta(Some(*total))),
+        ),
+    )
+    .spacing(20);
+    let stronger = Row::with_children(comparison.stronger.iter().enumerate().map(
+        |(sector, stronger)| {
+            let verdict = match stronger {
+                Some(Stronger::A) => format!("S{}: #{} faster", sector + 1, number_a),
+                Some(Stronger::B) => format!("S{}: #{} faster", sector + 1, number_b),
+                None => format!("S{}: even", sector + 1),
+            };
+            text(verdict).into()
+        },
+    ))
+    .spacing(20);
+
+    column![
+        text(format!(
+            "{} laps compared, deltas are #{} against #{}",
+            comparison.laps.len(),
+            number_b,
+            number_a
+        )),
+        stronger,
+        stint,
+        header,
+        scrollable(Column::with_children(laps).spacing(2)).height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
+
+pub fn view(session: &Session, a: Option<u16>, b: Option<u16>) -> Element<'_, Message> {
+    let a = a.or(session.focused_car());
+    let cars = (
+        a.and_then(|index| session.cars.get(&index)),
+        b.and_then(|index| session.cars.get(&index)),
+    );
+    let body = match cars {
+        (Some(a), Some(b)) => comparison(a, b),
+        _ => text("pick two cars").into(),
+    };
+    column![
+        picker(session, Side::A, a),
+        picker(session, Side::B, b),
+        body
+    ]
+    .spacing(6)
+    .into()
+}
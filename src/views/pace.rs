@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+
+use iced::{
+    widget::{button, column, row, text, Canvas, Column, Row},
+    Color, Element,
+    Length::Fill,
+};
+
+use crate::{
+    pace::{self, Pace},
+    session::Session,
+    utils,
+    views::{
+        charts::{LineChart, Series},
+        class_color,
+    },
+    Message,
+};
+
+/// colors handed to the compared cars in selection order
+const PALETTE: [Color; 8] = [
+    Color::from_rgb(0.9, 0.3, 0.3),
+    Color::from_rgb(0.3, 0.6, 0.9),
+    Color::from_rgb(0.3, 0.8, 0.4),
+    Color::from_rgb(0.9, 0.8, 0.2),
+    Color::from_rgb(0.8, 0.4, 0.9),
+    Color::from_rgb(0.9, 0.6, 0.2),
+    Color::from_rgb(0.3, 0.8, 0.8),
+    Color::from_rgb(0.8, 0.8, 0.8),
+];
+
+fn seconds(ms: f32) -> String {
+    format!("{:.3}", ms / 1000.0)
+}
+
+fn laptime_label(ms: f32) -> String {
+    utils::ms_to_string(ms.max(0.0) as u32)
+}
+
+fn lap_label(lap: f32) -> String {
+    format!("L{:.0}", lap)
+}
+
+/// every lap as a dot, laps that do not count for pace dimmed, with the
+/// rolling average through the clean ones
+fn scatter(paces: &[(Color, Pace)]) -> LineChart {
+    let series = paces
+        .iter()
+        .flat_map(|(color, pace)| {
+            let dots = |clean: bool| Series {
+                color: if clean {
+                    *color
+                } else {
+                    Color { a: 0.35, ..*color }
+                },
+                focused: false,
+                dots: true,
+                points: pace
+                    .laps
+                    .iter()
+                    .filter(|lap| lap.clean == clean && lap.laptime_ms < i32::MAX as u32)
+                    .map(|lap| {
+                        (
+                            lap.number as f32,
+                            lap.laptime_ms as f32,
+                            format!(
+                                "#{} L{} {}{}",
+                                pace.race_number,
+                                lap.number,
+                                utils::ms_to_string(lap.laptime_ms),
+                                if clean { "" } else { " (not counted)" }
+                            ),
+                        )
+                    })
+                    .collect(),
+            };
+            let rolling = Series {
+                color: *color,
+                focused: true,
+                dots: false,
+                points: pace
+                    .rolling
+                    .iter()
+                    .map(|(lap, average)| {
+                        (
+                            *lap as f32,
+                            *average,
+                            format!("#{} L{} avg {}", pace.race_number, lap, seconds(*average)),
+                        )
+                    })
+                    .collect(),
+            };
+            [dots(false), dots(true), rolling]
+        })
+        .collect();
+    LineChart {
+        series,
+        x_label: lap_label,
+        y_label: laptime_label,
+    }
+}
+
+fn fuel_corrected(paces: &[(Color, Pace)]) -> LineChart {
+    let series = paces
+        .iter()
+        .map(|(color, pace)| Series {
+            color: *color,
+            focused: false,
+            dots: false,
+            points: pace
+                .laps
+                .iter()
+                .filter(|lap| lap.clean)
+                .map(|lap| {
+                    (
+                        lap.number as f32,
+                        lap.fuel_corrected_ms,
+                        format!(
+                            "#{} L{} stint lap {} corrected {}",
+                            pace.race_number,
+                            lap.number,
+                            lap.stint_lap,
+                            seconds(lap.fuel_corrected_ms)
+                        ),
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+    LineChart {
+        series,
+        x_label: lap_label,
+        y_label: laptime_label,
+    }
+}
+
+pub fn view<'a>(session: &'a Session, selected: &BTreeSet<u16>) -> Element<'a, Message> {
+    let mut selected: Vec<u16> = selected.iter().copied().collect();
+    if selected.is_empty() {
+        selected.extend(session.focused_car());
+    }
+    let fuel_per_lap = session.fuel.per_lap().unwrap_or(pace::DEFAULT_FUEL_PER_LAP);
+    let paces: Vec<(Color, Pace)> = selected
+        .iter()
+        .filter_map(|index| session.cars.get(index))
+        .zip(PALETTE.iter().cycle())
+        .map(|(car, color)| (*color, pace::analyze(car, fuel_per_lap)))
+        .collect();
+
+    let picker = Row::with_children(session.standings().into_iter().map(|car| {
+        let index = car.car_info.car_index;
+        let label = if selected.contains(&index) {
+            format!("[#{}]", car.car_info.race_number)
+        } else {
+            format!("#{}", car.car_info.race_number)
+        };
+        button(text(label).color(class_color(car.class())))
+            .on_press(Message::TogglePaceCar(index))
+            .into()
+    }))
+    .spacing(4)
+    .wrap();
+
+    let table = Column::with_children(paces.iter().map(|(color, pace)| {
+        let clean = pace.laps.iter().filter(|lap| lap.clean).count();
+        row![
+            text(format!("#{}", pace.race_number)).color(*color),
+            text(pace.class.name()).color(class_color(pace.class)),
+            text(format!("{} clean laps", clean)),
+            text(format!(
+                "mean {}",
+                pace.mean_ms.map_or(String::from("-"), seconds)
+            )),
+            text(format!(
+                "stddev {}",
+                pace.stddev_ms.map_or(String::from("-"), seconds)
+            )),
+            text(format!(
+                "rolling {}",
+                pace.rolling
+                    .last()
+                    .map_or(String::from("-"), |(_, average)| seconds(*average))
+            )),
+            text(format!(
+                "fuel corrected {}",
+                pace.fuel_corrected_trend
+                    .map_or(String::from("-"), |trend| format!(
+                        "{:+.3}s/lap",
+                        trend / 1000.0
+                    ))
+            ))
+        ]
+        .spacing(10)
+        .into()
+    }))
+    .spacing(2);
+
+    column![
+        picker,
+        table,
+        text("clean laps and rolling average"),
+        Canvas::new(scatter(&paces)).width(Fill).height(Fill),
+        text(format!("fuel corrected pace ({:.1} l/lap)", fuel_per_lap)),
+        Canvas::new(fuel_corrected(&paces)).width(Fill).height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
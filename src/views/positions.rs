@@ -0,0 +1,72 @@
+use iced::{
+    widget::{button, column, row, scrollable, text, Column, Row},
+    Color, Element,
+    Length::Fill,
+};
+
+use crate::{positions::ChangeKind, session::Session, utils, views::class_color, Message};
+
+const FOCUS_COLOR: Color = Color::from_rgb(1.0, 0.85, 0.3);
+
+/// Which position changes the log window shows
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Filter {
+    pub kind: Option<ChangeKind>,
+    /// only changes our car was part of
+    pub ours: bool,
+}
+
+pub fn view(session: &Session, filter: Filter) -> Element<'_, Message> {
+    let focused = session.focused_car();
+    let rows = session
+        .position_log
+        .iter()
+        .rev()
+        .filter(|change| filter.kind.is_none_or(|kind| change.kind == kind))
+        .filter(|change| !filter.ours || focused.is_some_and(|car| change.involves(car)))
+        .map(|change| {
+            let ours = focused.is_some_and(|car| change.involves(car));
+            row![
+                text(utils::ms_to_string(change.session_time as u32)),
+                text(format!("L{}", change.lap)),
+                text(change.class.name()).color(class_color(change.class)),
+                text(change.describe()).color_maybe(ours.then_some(FOCUS_COLOR))
+            ]
+            .spacing(6)
+            .into()
+        });
+
+    let kinds = Row::with_children(
+        std::iter::once(
+            button("all")
+                .on_press(Message::PositionFilter(Filter {
+                    kind: None,
+                    ..filter
+                }))
+                .into(),
+        )
+        .chain(ChangeKind::ALL.into_iter().map(|kind| {
+            button(kind.name())
+                .on_press(Message::PositionFilter(Filter {
+                    kind: Some(kind),
+                    ..filter
+                }))
+                .into()
+        })),
+    )
+    .spacing(4);
+    let ours = button(if filter.ours { "all cars" } else { "our car" }).on_press(
+        Message::PositionFilter(Filter {
+            ours: !filter.ours,
+            ..filter
+        }),
+    );
+
+    column![
+        row![kinds, ours].spacing(10),
+        text(format!("{} position changes", session.position_log.len())),
+        scrollable(Column::with_children(rows).spacing(2)).height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
@@ -0,0 +1,46 @@
+use iced::{
+    widget::{column, row, scrollable, text, Column},
+    Color, Element,
+    Length::Fill,
+};
+
+use crate::{session::Session, udp::BroadcastingEventType, utils, Message};
+
+const PENALTY_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+const ACCIDENT_COLOR: Color = Color::from_rgb(0.9, 0.2, 0.2);
+const GREEN_COLOR: Color = Color::from_rgb(0.3, 0.8, 0.3);
+const LAP_COLOR: Color = Color::from_rgb(0.6, 0.6, 0.6);
+
+pub fn view(session: &Session) -> Element<'_, Message> {
+    let focused = session.focused_car();
+    let rows = session.race_control.iter().rev().map(|entry| {
+        let color = match entry.event_type {
+            BroadcastingEventType::PenaltyCommMsg => Some(PENALTY_COLOR),
+            BroadcastingEventType::Accident => Some(ACCIDENT_COLOR),
+            BroadcastingEventType::GreenFlag => Some(GREEN_COLOR),
+            BroadcastingEventType::LapCompleted | BroadcastingEventType::BestPersonalLap => {
+                Some(LAP_COLOR)
+            }
+            _ => None,
+        };
+        let ours = entry.car_index.is_some() && entry.car_index == focused;
+        row![
+            text(utils::ms_to_string(entry.session_time as u32)),
+            text(if ours { ">" } else { "" }),
+            text(&entry.text).color_maybe(color)
+        ]
+        .spacing(6)
+        .into()
+    });
+
+    column![
+        text(format!(
+            "race control: {} messages, {} accidents",
+            session.race_control.len(),
+            session.accidents.len()
+        )),
+        scrollable(Column::with_children(rows).spacing(2)).height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
@@ -0,0 +1,133 @@
+use iced::{
+    widget::{column, row, text, Column},
+    Color, Element,
+};
+
+use crate::{
+    car_models::CarClass,
+    pit::{self, LapRelation},
+    session::Session,
+    views::class_color,
+    Message,
+};
+
+const GHOST_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.9);
+const LAPPED_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
+const LAPPING_COLOR: Color = Color::from_rgb(0.9, 0.3, 0.3);
+
+enum Entry {
+    Car {
+        race_number: u32,
+        class: CarClass,
+        gap: f32,
+    },
+    Ghost {
+        gap: f32,
+    },
+}
+
+impl Entry {
+    fn gap(&self) -> f32 {
+        match self {
+            Entry::Car { gap, .. } | Entry::Ghost { gap } => *gap,
+        }
+    }
+}
+
+/// cars ordered by their gap on track to the focused car, with a ghost
+/// marker for where the focused car would rejoin if it pitted this lap
+pub fn view(session: &Session) -> Element<'_, Message> {
+    let Some(focused) = session.focused_car() else {
+        return text("waiting for focused car").into();
+    };
+    let car = &session.cars[&focused];
+    let Some(pace) = car.rolling_pace() else {
+        return text("waiting for lap times").into();
+    };
+    let to_seconds = |spline: f32| pit::spline_delta(car.spline_position(), spline) * pace / 1000.0;
+
+    let mut entries: Vec<Entry> = session
+        .cars
+        .values()
+        .filter(|other| other.realtime.is_some())
+        .map(|other| Entry::Car {
+            race_number: other.car_info.race_number,
+            class: other.class(),
+            gap: to_seconds(other.spline_position()),
+        })
+        .collect();
+
+    let rejoin = pit::predict_rejoin(session, focused);
+    if let Some(rejoin) = &rejoin {
+        entries.push(Entry::Ghost {
+            gap: to_seconds(rejoin.spline_position),
+        });
+    }
+    entries.sort_by(|a, b| b.gap().total_cmp(&a.gap()));
+
+    let board = Column::with_children(entries.into_iter().map(|entry| {
+        match entry {
+            Entry::Car {
+                race_number,
+                class,
+                gap,
+            } => row![
+                text(race_number).color(class_color(class)),
+                text(format!("{:+.1}", gap))
+            ]
+            .spacing(4)
+            .into(),
+            Entry::Ghost { gap } => row![
+                text("PIT").color(GHOST_COLOR),
+                text(format!("{:+.1}", gap)).color(GHOST_COLOR)
+            ]
+            .spacing(4)
+            .into(),
+        }
+    }));
+
+    let traffic: Element<'_, Message> = match rejoin {
+        None => text(format!(
+            "pit loss: measuring ({} stops)",
+            session.pit_model.sample_count()
+        ))
+        .into(),
+        Some(rejoin) => {
+            let header = if session.neutral.is_active() {
+                text(format!(
+                    "pit under yellow: rejoin with {:.1}s pit loss",
+                    rejoin.pit_loss / 1000.0
+                ))
+                .color(GHOST_COLOR)
+            } else {
+                text(format!(
+                    "rejoin with {:.1}s pit loss",
+                    rejoin.pit_loss / 1000.0
+                ))
+            };
+            let cars = rejoin.traffic.into_iter().map(|other| {
+                let (label, color) = match other.relation {
+                    LapRelation::SameLap => ("", None),
+                    LapRelation::Lapped => ("lapped", Some(LAPPED_COLOR)),
+                    LapRelation::Lapping => ("lapping", Some(LAPPING_COLOR)),
+                };
+                let class = if other.same_class {
+                    "class"
+                } else {
+                    "other class"
+                };
+                row![
+                    text(other.race_number).color_maybe(color),
+                    text(format!("{:+.1}", other.gap)).color_maybe(color),
+                    text(class),
+                    text(label)
+                ]
+                .spacing(4)
+                .into()
+            });
+            column![header, Column::with_children(cars)].into()
+        }
+    };
+
+    column![board, traffic].spacing(10).into()
+}
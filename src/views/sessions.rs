@@ -0,0 +1,45 @@
+use iced::{
+    widget::{button, column, row, scrollable, text, Column},
+    Color, Element,
+    Length::Fill,
+};
+
+use crate::{archive::Summary, Message};
+
+const OPEN_COLOR: Color = Color::from_rgb(0.3, 0.6, 0.9);
+
+/// lists archived sessions, `open` is the one currently shown instead of
+/// the live session
+pub fn view(sessions: &[Summary], open: Option<i64>) -> Element<'_, Message> {
+    let rows = sessions.iter().map(|summary| {
+        let color = (open == Some(summary.id)).then_some(OPEN_COLOR);
+        row![
+            text(&summary.date).color_maybe(color),
+            text(summary.track_name.as_deref().unwrap_or("unknown track")).color_maybe(color),
+            text(&summary.session_type).color_maybe(color),
+            text(format!("{:.0} min", summary.session_time / 60_000.0)),
+            text(format!("{} cars", summary.cars)),
+            button("open").on_press(Message::OpenArchived(summary.id))
+        ]
+        .spacing(10)
+        .into()
+    });
+
+    let live = button(if open.is_some() {
+        "back to live"
+    } else {
+        "showing live"
+    })
+    .on_press_maybe(open.map(|_| Message::BackToLive));
+    column![
+        row![
+            text(format!("{} archived sessions", sessions.len())),
+            button("refresh").on_press(Message::RefreshArchive),
+            live
+        ]
+        .spacing(10),
+        scrollable(Column::with_children(rows).spacing(2)).height(Fill)
+    ]
+    .spacing(6)
+    .into()
+}
@@ -0,0 +1,137 @@
+use iced::{
+    widget::{button, column, container, row, text, Column, Row},
+    Color, Element,
+};
+
+use crate::{
+    car_models::CarClass,
+    classification::{self, Order},
+    session::Session,
+    utils,
+    views::class_color,
+    Message,
+};
+
+const PENALTY_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+
+/// cup categories as ACC numbers them
+fn cup_category(category: u8) -> &'static str {
+    match category {
+        0 => "Pro",
+        1 => "ProAm",
+        2 => "Am",
+        3 => "Silver",
+        4 => "National",
+        _ => "-",
+    }
+}
+
+/// `read_only` disables the mandatory stop buttons, for archived sessions
+pub fn view(
+    session: &Session,
+    order: Order,
+    filter: Option<CarClass>,
+    read_only: bool,
+) -> Element<'_, Message> {
+    let projection = &session.projection;
+
+    let rows = classification::ordered(session, order, filter)
+        .into_iter()
+        .map(|entry| {
+            let car = entry.car;
+            let color = class_color(entry.class);
+            let laptime = car.laps.last().map_or(0, |lap| lap.info.laptime_ms);
+            let cup_position = car
+                .realtime
+                .as_ref()
+                .map_or(0, |update| update.cup_position);
+            let projected = projection
+                .as_ref()
+                .and_then(|projection| projection.cars.get(&car.car_info.car_index))
+                .map_or(String::from("-"), |projected| {
+                    format!("P{} L{}", projected.position, projected.finishing_lap)
+                });
+            let first = match order {
+                Order::Overall => car.position(),
+                Order::Class => entry.class_position,
+            };
+            container(
+                row![
+                    text(first),
+                    text(format!("{} P{}", entry.class, entry.class_position)).color(color),
+                    text(car.car_info.race_number).color(color),
+                    text(car.model_name()),
+                    text(format!(
+                        "{} P{}",
+                        cup_category(car.car_info.cup_category),
+                        cup_position
+                    )),
+                    text(entry.gap_to_leader.to_string()),
+                    text(entry.gap_to_class_leader.to_string()).color(color),
+                    text(utils::ms_to_string(laptime)),
+                    text(projected),
+                    text(
+                        car.penalties
+                            .last()
+                            .map_or(String::new(), |penalty| penalty.describe())
+                    )
+                    .color(PENALTY_COLOR)
+                ]
+                .spacing(6),
+            )
+            .into()
+        });
+
+    let mut summary = match (projection, session.focused_car()) {
+        (Some(projection), Some(focused)) => {
+            let to_go = projection
+                .cars
+                .get(&focused)
+                .map_or(String::from("-"), |car| car.laps_to_go.to_string());
+            format!(
+                "leader finishes on lap {}, {} laps to go for us",
+                projection.leader_laps, to_go
+            )
+        }
+        (Some(projection), None) => format!("leader finishes on lap {}", projection.leader_laps),
+        (None, _) => String::from("projection: waiting for pace"),
+    };
+    if let Some(liters) = session.splash_and_dash() {
+        summary.push_str(&format!(", splash and dash: {:.1} l short", liters));
+    }
+
+    let stops = row![
+        text(format!("mandatory stops: {}", session.mandatory_stops)),
+        button("-").on_press_maybe((!read_only).then_some(Message::MandatoryStops(
+            session.mandatory_stops.saturating_sub(1)
+        ))),
+        button("+").on_press_maybe(
+            (!read_only).then_some(Message::MandatoryStops(session.mandatory_stops + 1))
+        ),
+    ]
+    .spacing(4);
+
+    let toggle = match order {
+        Order::Overall => button("class order").on_press(Message::StandingsOrder(Order::Class)),
+        Order::Class => button("overall order").on_press(Message::StandingsOrder(Order::Overall)),
+    };
+    let filters = Row::with_children(
+        std::iter::once(button("all").on_press(Message::ClassFilter(None)).into()).chain(
+            classification::classes(session).into_iter().map(|class| {
+                button(text(class.name()).color(class_color(class)))
+                    .on_press(Message::ClassFilter(Some(class)))
+                    .into()
+            }),
+        ),
+    )
+    .spacing(4);
+
+    column![
+        stops,
+        text(summary),
+        row![toggle, filters].spacing(10),
+        Column::with_children(rows)
+    ]
+    .spacing(4)
+    .into()
+}
@@ -0,0 +1,312 @@
+use iced::{
+    alignment, mouse,
+    widget::{
+        canvas::{self, Frame, Geometry, Path, Stroke, Text},
+        column, text, Canvas,
+    },
+    Color, Element,
+    Length::Fill,
+    Point, Rectangle, Renderer, Theme, Vector,
+};
+
+use crate::{
+    hazards,
+    session::Session,
+    track::{OutlinePoint, TrackRecord},
+    utils,
+    views::class_color,
+    Message,
+};
+
+const MARGIN: f32 = 30.0;
+const CAR_RADIUS: f32 = 6.0;
+const HEADING_LENGTH: f32 = 14.0;
+const TRACK_COLOR: Color = Color::from_rgb(0.45, 0.45, 0.45);
+const PIT_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+const FOCUS_COLOR: Color = Color::WHITE;
+const SECTOR_COLOR: Color = Color::from_rgb(0.8, 0.8, 0.8);
+const CATCH_COLOR: Color = Color::from_rgb(1.0, 0.5, 0.0);
+const CATCH_SIZE: f32 = 6.0;
+const ACCIDENT_COLOR: Color = Color::from_rgb(0.9, 0.2, 0.2);
+const ACCIDENT_SIZE: f32 = 8.0;
+const HAZARD_COLOR: Color = Color::from_rgb(1.0, 0.9, 0.0);
+/// points the flagged part of the track around a hazard is drawn with
+const ZONE_POINTS: usize = 10;
+
+/// Maps world positions onto the canvas, rotating the track so its long
+/// axis follows the long side of the window
+struct Transform {
+    center: (f32, f32),
+    cos: f32,
+    sin: f32,
+    scale: f32,
+    offset: Vector,
+}
+
+impl Transform {
+    fn fit(points: &[(f32, f32)], bounds: Rectangle) -> Option<Self> {
+        if points.len() < 2 {
+            return None;
+        }
+        let n = points.len() as f32;
+        let cx = points.iter().map(|p| p.0).sum::<f32>() / n;
+        let cy = points.iter().map(|p| p.1).sum::<f32>() / n;
+        let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+        for (x, y) in points {
+            sxx += (x - cx) * (x - cx);
+            syy += (y - cy) * (y - cy);
+            sxy += (x - cx) * (y - cy);
+        }
+        // principal axis of the outline
+        let mut angle = -0.5 * (2.0 * sxy).atan2(sxx - syy);
+        if bounds.height > bounds.width {
+            angle += std::f32::consts::FRAC_PI_2;
+        }
+
+        let mut transform = Transform {
+            center: (cx, cy),
+            cos: angle.cos(),
+            sin: angle.sin(),
+            scale: 1.0,
+            offset: Vector::new(0.0, 0.0),
+        };
+        let rotated: Vec<Point> = points.iter().map(|p| transform.rotate(*p)).collect();
+        let min_x = rotated.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let max_x = rotated.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        let min_y = rotated.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let max_y = rotated.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        let width = (max_x - min_x).max(1.0);
+        let height = (max_y - min_y).max(1.0);
+
+        transform.scale = ((bounds.width - 2.0 * MARGIN) / width)
+            .min((bounds.height - 2.0 * MARGIN) / height)
+            .max(0.0);
+        transform.offset = Vector::new(
+            bounds.width / 2.0 - (min_x + width / 2.0) * transform.scale,
+            bounds.height / 2.0 - (min_y + height / 2.0) * transform.scale,
+        );
+        Some(transform)
+    }
+
+    /// rotation around the outline center, y flipped for screen space
+    fn rotate(&self, (x, y): (f32, f32)) -> Point {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        Point::new(
+            dx * self.cos - dy * self.sin,
+            -(dx * self.sin + dy * self.cos),
+        )
+    }
+
+    fn project(&self, point: (f32, f32)) -> Point {
+        let rotated = self.rotate(point);
+        Point::new(
+            rotated.x * self.scale + self.offset.x,
+            rotated.y * self.scale + self.offset.y,
+        )
+    }
+
+    /// screen direction of a world heading
+    fn direction(&self, yaw: f32) -> Vector {
+        let (dx, dy) = (yaw.cos(), yaw.sin());
+        Vector::new(
+            dx * self.cos - dy * self.sin,
+            -(dx * self.sin + dy * self.cos),
+        )
+    }
+}
+
+/// polyline through the known points, broken where bins are missing
+fn line_path(points: &[OutlinePoint], transform: &Transform, closed: bool) -> Path {
+    Path::new(|builder| {
+        let mut drawing = false;
+        for point in points {
+            if !point.is_known() {
+                drawing = false;
+                continue;
+            }
+            let projected = transform.project((point.x, point.y));
+            if drawing {
+                builder.line_to(projected);
+            } else {
+                builder.move_to(projected);
+                drawing = true;
+            }
+        }
+        let (first, last) = (points.first(), points.last());
+        if let (true, Some(first), Some(last)) = (closed, first, last) {
+            if first.is_known() && last.is_known() {
+                builder.line_to(transform.project((first.x, first.y)));
+            }
+        }
+    })
+}
+
+struct TrackMap<'a> {
+    session: &'a Session,
+    outline: &'a TrackRecord,
+}
+
+impl canvas::Program<Message> for TrackMap<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let known: Vec<(f32, f32)> = self
+            .outline
+            .racing_line
+            .iter()
+            .chain(self.outline.pit_lane.iter())
+            .filter(|point| point.is_known())
+            .map(|point| (point.x, point.y))
+            .collect();
+        let Some(transform) = Transform::fit(&known, bounds) else {
+            return vec![frame.into_geometry()];
+        };
+
+        frame.stroke(
+            &line_path(&self.outline.racing_line, &transform, true),
+            Stroke::default().with_color(TRACK_COLOR).with_width(8.0),
+        );
+        frame.stroke(
+            &line_path(&self.outline.pit_lane, &transform, false),
+            Stroke::default().with_color(PIT_COLOR).with_width(4.0),
+        );
+
+        for (i, boundary) in self.outline.sectors.iter().enumerate() {
+            let Some(point) = self.outline.point_at(boundary.spline_position) else {
+                continue;
+            };
+            let center = transform.project(point);
+            frame.fill(&Path::circle(center, 3.0), SECTOR_COLOR);
+            frame.fill_text(Text {
+                content: format!("S{}", i + 2),
+                position: center + Vector::new(4.0, 4.0),
+                color: SECTOR_COLOR,
+                ..Text::default()
+            });
+        }
+
+        for hazard in &self.session.hazards.hazards {
+            let zone: Vec<OutlinePoint> = (0..=ZONE_POINTS)
+                .map(|i| {
+                    let spline = hazard.spline_position - hazards::ZONE
+                        + 2.0 * hazards::ZONE * i as f32 / ZONE_POINTS as f32;
+                    self.outline
+                        .point_at(spline)
+                        .map_or(OutlinePoint::default(), |(x, y)| OutlinePoint {
+                            x,
+                            y,
+                            samples: 1,
+                        })
+                })
+                .collect();
+            frame.stroke(
+                &line_path(&zone, &transform, false),
+                Stroke::default().with_color(HAZARD_COLOR).with_width(10.0),
+            );
+        }
+
+        for accident in &self.session.accidents {
+            let Some(point) = self.outline.point_at(accident.spline_position) else {
+                continue;
+            };
+            let center = transform.project(point);
+            let triangle = Path::new(|builder| {
+                builder.move_to(center + Vector::new(0.0, -ACCIDENT_SIZE));
+                builder.line_to(center + Vector::new(ACCIDENT_SIZE, ACCIDENT_SIZE));
+                builder.line_to(center + Vector::new(-ACCIDENT_SIZE, ACCIDENT_SIZE));
+                builder.close();
+            });
+            frame.stroke(
+                &triangle,
+                Stroke::default().with_color(ACCIDENT_COLOR).with_width(2.0),
+            );
+            frame.fill_text(Text {
+                content: format!(
+                    "{} {}",
+                    accident
+                        .race_number
+                        .map_or(String::from("!"), |number| format!("#{}", number)),
+                    utils::ms_to_string(accident.session_time as u32)
+                ),
+                position: center + Vector::new(ACCIDENT_SIZE + 2.0, 0.0),
+                color: ACCIDENT_COLOR,
+                ..Text::default()
+            });
+        }
+
+        for warning in &self.session.traffic {
+            let Some(point) = self.outline.point_at(warning.catch_spline) else {
+                continue;
+            };
+            let center = transform.project(point);
+            let cross = Path::new(|builder| {
+                builder.move_to(center + Vector::new(-CATCH_SIZE, -CATCH_SIZE));
+                builder.line_to(center + Vector::new(CATCH_SIZE, CATCH_SIZE));
+                builder.move_to(center + Vector::new(-CATCH_SIZE, CATCH_SIZE));
+                builder.line_to(center + Vector::new(CATCH_SIZE, -CATCH_SIZE));
+            });
+            frame.stroke(
+                &cross,
+                Stroke::default().with_color(CATCH_COLOR).with_width(3.0),
+            );
+            frame.fill_text(Text {
+                content: format!("#{} {:.1}s", warning.race_number, warning.time_to_catch),
+                position: center + Vector::new(CATCH_SIZE + 2.0, CATCH_SIZE),
+                color: CATCH_COLOR,
+                ..Text::default()
+            });
+        }
+
+        let focused = self.session.focused_car();
+        for car in self.session.cars.values() {
+            let Some(update) = &car.realtime else {
+                continue;
+            };
+            let center = transform.project((update.world_pos_x, update.world_pos_y));
+            let color = class_color(car.class());
+            let is_focused = focused == Some(car.car_info.car_index);
+
+            if is_focused {
+                frame.fill(&Path::circle(center, CAR_RADIUS + 3.0), FOCUS_COLOR);
+            }
+            frame.fill(&Path::circle(center, CAR_RADIUS), color);
+            let heading = transform.direction(update.yaw) * HEADING_LENGTH;
+            frame.stroke(
+                &Path::line(center, center + heading),
+                Stroke::default().with_color(color).with_width(2.0),
+            );
+            frame.fill_text(Text {
+                content: car.car_info.race_number.to_string(),
+                position: center + Vector::new(CAR_RADIUS + 2.0, -CAR_RADIUS - 2.0),
+                color: if is_focused { FOCUS_COLOR } else { color },
+                vertical_alignment: alignment::Vertical::Bottom,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+pub fn view(session: &Session) -> Element<'_, Message> {
+    let Some(outline) = &session.track_record else {
+        return text("track map: waiting for track data").into();
+    };
+    let header = text(format!(
+        "{} — outline {:.0}% learned",
+        outline.track_name,
+        outline.coverage() * 100.0
+    ));
+    let map = Canvas::new(TrackMap { session, outline })
+        .width(Fill)
+        .height(Fill);
+    column![header, map].into()
+}
@@ -0,0 +1,117 @@
+use iced::{
+    widget::{button, column, row, text, Column},
+    Color, Element,
+};
+
+use crate::{
+    session::Session,
+    tyres::{Compound, TyreAction},
+    utils, Message,
+};
+
+const WARNING_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+
+/// disabled when `read_only`
+fn tyre_button(
+    label: &str,
+    action: TyreAction,
+    read_only: bool,
+) -> iced::widget::Button<'_, Message> {
+    button(text(label)).on_press_maybe((!read_only).then_some(Message::Tyres(action)))
+}
+
+/// `read_only` disables every edit, for archived sessions
+pub fn view(session: &Session, read_only: bool) -> Element<'_, Message> {
+    let tyres = &session.tyres;
+    let action = |label, action| tyre_button(label, action, read_only);
+
+    let allocation = row![
+        text(format!(
+            "dry sets: {} used / {}",
+            tyres.dry_sets_used(),
+            tyres.dry_sets
+        )),
+        action(
+            "-",
+            TyreAction::SetDrySets(tyres.dry_sets.saturating_sub(1))
+        ),
+        action("+", TyreAction::SetDrySets(tyres.dry_sets + 1)),
+    ]
+    .spacing(4);
+
+    let sets = Column::with_children(tyres.sets.iter().map(|set| {
+        let trend = set.wear_trend().map_or(String::from("-"), |trend| {
+            format!("{:+.2}s/lap", trend / 1000.0)
+        });
+        let best = set
+            .laptimes
+            .iter()
+            .min()
+            .map_or(String::from("-"), |best| utils::ms_to_string(*best));
+        let pressure = set.avg_pressure.map_or(String::new(), |psi| {
+            format!(
+                "{:.1} {:.1} {:.1} {:.1} psi",
+                psi[0], psi[1], psi[2], psi[3]
+            )
+        });
+        row![
+            text(format!("set {}", set.number)),
+            text(format!("{:?}", set.compound)),
+            text(format!("from L{}", set.fitted_on_lap)),
+            text(format!("{} laps", set.laps)),
+            text(best),
+            text(trend),
+            text(pressure),
+        ]
+        .spacing(6)
+        .into()
+    }));
+
+    let current = row![
+        action("dry", TyreAction::SetCompound(Compound::Dry)),
+        action("wet", TyreAction::SetCompound(Compound::Wet)),
+        action("no change at last stop", TyreAction::KeepPreviousSet),
+        action("reset", TyreAction::Reset),
+    ]
+    .spacing(4);
+
+    let plan = Column::with_children(tyres.plan.iter().enumerate().map(|(index, stint)| {
+        row![
+            text(format!("stint {}", index + 1)),
+            action(
+                "-",
+                TyreAction::SetStintLaps(index, stint.laps.saturating_sub(1))
+            ),
+            text(format!("{} laps", stint.laps)),
+            action("+", TyreAction::SetStintLaps(index, stint.laps + 1)),
+            action(
+                if stint.new_set { "new set" } else { "reuse" },
+                TyreAction::ToggleNewSet(index)
+            ),
+            action("x", TyreAction::RemoveStint(index)),
+        ]
+        .spacing(4)
+        .into()
+    }));
+
+    let summary: Element<'_, Message> = match tyres.plan_warning() {
+        Some(warning) => text(warning).color(WARNING_COLOR).into(),
+        None => text(format!(
+            "plan uses {} of {} remaining sets",
+            tyres.planned_new_sets(),
+            tyres.remaining_dry_sets()
+        ))
+        .into(),
+    };
+
+    column![
+        allocation,
+        sets,
+        current,
+        plan,
+        action("add stint", TyreAction::AddStint),
+        summary
+    ]
+    .spacing(6)
+    .into()
+}
@@ -0,0 +1,132 @@
+use iced::{
+    mouse,
+    widget::{
+        canvas::{self, Frame, Geometry, Path, Stroke, Text},
+        column, text, Canvas,
+    },
+    Color, Element,
+    Length::Fill,
+    Point, Rectangle, Renderer, Theme,
+};
+
+use crate::{session::Session, weather::WeatherSample, Message};
+
+const CLOUDS_COLOR: Color = Color::from_rgb(0.6, 0.6, 0.6);
+const RAIN_COLOR: Color = Color::from_rgb(0.2, 0.4, 0.9);
+const WETNESS_COLOR: Color = Color::from_rgb(0.2, 0.8, 0.8);
+const AIR_COLOR: Color = Color::from_rgb(0.9, 0.8, 0.2);
+const TRACK_COLOR: Color = Color::from_rgb(0.9, 0.3, 0.2);
+
+struct WeatherChart<'a> {
+    samples: &'a [WeatherSample],
+}
+
+impl WeatherChart<'_> {
+    fn line(
+        &self,
+        frame: &mut Frame,
+        bounds: Rectangle,
+        color: Color,
+        value: impl Fn(&WeatherSample) -> f32,
+    ) {
+        let start = self.samples[0].session_time;
+        let span = (self.samples.last().unwrap().session_time - start).max(1.0);
+        let path = Path::new(|builder| {
+            for (i, sample) in self.samples.iter().enumerate() {
+                let point = Point::new(
+                    (sample.session_time - start) / span * bounds.width,
+                    (1.0 - value(sample).clamp(0.0, 1.0)) * bounds.height,
+                );
+                if i == 0 {
+                    builder.move_to(point);
+                } else {
+                    builder.line_to(point);
+                }
+            }
+        });
+        frame.stroke(&path, Stroke::default().with_color(color).with_width(2.0));
+    }
+}
+
+impl canvas::Program<Message> for WeatherChart<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        if self.samples.len() < 2 {
+            return vec![frame.into_geometry()];
+        }
+
+        // temperatures share the chart scaled between their extremes
+        let temps = self
+            .samples
+            .iter()
+            .flat_map(|sample| [sample.ambient_temp, sample.track_temp]);
+        let low = temps.clone().min().unwrap_or(0) as f32 - 1.0;
+        let high = temps.max().unwrap_or(0) as f32 + 1.0;
+        let scale_temp = |temp: u8| (temp as f32 - low) / (high - low);
+
+        self.line(&mut frame, bounds, CLOUDS_COLOR, |sample| sample.clouds);
+        self.line(&mut frame, bounds, RAIN_COLOR, |sample| sample.rain_level);
+        self.line(&mut frame, bounds, WETNESS_COLOR, |sample| sample.wetness);
+        self.line(&mut frame, bounds, AIR_COLOR, |sample| {
+            scale_temp(sample.ambient_temp)
+        });
+        self.line(&mut frame, bounds, TRACK_COLOR, |sample| {
+            scale_temp(sample.track_temp)
+        });
+
+        for (i, (label, color)) in [
+            ("clouds", CLOUDS_COLOR),
+            ("rain", RAIN_COLOR),
+            ("wetness", WETNESS_COLOR),
+            ("air", AIR_COLOR),
+            ("track", TRACK_COLOR),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            frame.fill_text(Text {
+                content: label.to_string(),
+                position: Point::new(4.0 + i as f32 * 60.0, 4.0),
+                color,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+pub fn view(session: &Session) -> Element<'_, Message> {
+    let current =
+        session
+            .weather
+            .latest()
+            .map_or(String::from("weather: waiting for data"), |sample| {
+                format!(
+                    "air {}°C track {}°C clouds {:.0}% rain {:.0}% wet {:.0}% ({:?})",
+                    sample.ambient_temp,
+                    sample.track_temp,
+                    sample.clouds * 100.0,
+                    sample.rain_level * 100.0,
+                    sample.wetness * 100.0,
+                    session.weather.trend
+                )
+            });
+
+    let chart = Canvas::new(WeatherChart {
+        samples: &session.weather.samples,
+    })
+    .width(Fill)
+    .height(160);
+
+    column![text(current), chart].spacing(4).into()
+}
@@ -0,0 +1,165 @@
+//! Module for weather tracking
+//!
+//! Records the weather fields of `RealtimeUpdate` as a time series and
+//! raises strategy alerts when the trend changes.
+
+use log::debug;
+
+use crate::{alerts::Alert, udp};
+
+/// minimum session time between two samples in ms
+const SAMPLE_INTERVAL_MS: f32 = 10_000.0;
+/// how far back trends are measured in ms
+const TREND_WINDOW_MS: f32 = 300_000.0;
+/// smallest change in rain level or wetness counted as a trend, the fields
+/// come in steps of 0.1
+const TREND_THRESHOLD: f32 = 0.1;
+
+#[derive(Debug, Clone)]
+pub struct WeatherSample {
+    pub session_time: f32,
+    pub clouds: f32,
+    pub rain_level: f32,
+    pub wetness: f32,
+    pub ambient_temp: u8,
+    pub track_temp: u8,
+}
+
+impl From<&udp::RealtimeUpdate> for WeatherSample {
+    fn from(update: &udp::RealtimeUpdate) -> Self {
+        WeatherSample {
+            session_time: update.session_time,
+            clouds: update.clouds,
+            rain_level: update.rain_level,
+            wetness: update.wetness,
+            ambient_temp: update.ambiant_temp,
+            track_temp: update.track_temp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trend {
+    Stable,
+    RainStarting,
+    RainRising,
+    RainEasing,
+    RainStopped,
+    TrackDrying,
+    TrackWetting,
+}
+
+#[derive(Debug)]
+pub struct Weather {
+    pub samples: Vec<WeatherSample>,
+    pub trend: Trend,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            samples: vec![],
+            trend: Trend::Stable,
+        }
+    }
+}
+
+impl Weather {
+    pub fn latest(&self) -> Option<&WeatherSample> {
+        self.samples.last()
+    }
+
+    /// stores a sample and returns an alert when the trend changed
+    pub fn record(&mut self, update: &udp::RealtimeUpdate) -> Option<Alert> {
+        let sample = WeatherSample::from(update);
+        if let Some(last) = self.samples.last() {
+            if sample.session_time < last.session_time {
+                debug!("session clock went back, restarting weather history");
+                self.samples.clear();
+            } else if sample.session_time - last.session_time < SAMPLE_INTERVAL_MS {
+                return None;
+            }
+        }
+        self.samples.push(sample);
+
+        let trend = self.detect_trend();
+        if trend == self.trend {
+            return None;
+        }
+        debug!("weather trend {:?} -> {:?}", self.trend, trend);
+        self.trend = trend;
+        self.alert(trend)
+    }
+
+    fn detect_trend(&self) -> Trend {
+        let now = self.samples.last().unwrap();
+        let window: Vec<&WeatherSample> = self
+            .samples
+            .iter()
+            .filter(|sample| now.session_time - sample.session_time <= TREND_WINDOW_MS)
+            .collect();
+        let first = window[0];
+        let full_window =
+            now.session_time - first.session_time >= TREND_WINDOW_MS - SAMPLE_INTERVAL_MS;
+        let rain_delta = now.rain_level - first.rain_level;
+        let wet_delta = now.wetness - first.wetness;
+        let rain_never_fell = window
+            .windows(2)
+            .all(|pair| pair[1].rain_level >= pair[0].rain_level);
+
+        if first.rain_level == 0.0 && now.rain_level > 0.0 {
+            Trend::RainStarting
+        } else if full_window && rain_never_fell && rain_delta >= TREND_THRESHOLD {
+            Trend::RainRising
+        } else if rain_delta <= -TREND_THRESHOLD && now.rain_level > 0.0 {
+            Trend::RainEasing
+        } else if first.rain_level > 0.0 && now.rain_level == 0.0 {
+            Trend::RainStopped
+        } else if now.rain_level == 0.0 && wet_delta <= -TREND_THRESHOLD {
+            Trend::TrackDrying
+        } else if wet_delta >= TREND_THRESHOLD {
+            Trend::TrackWetting
+        } else {
+            Trend::Stable
+        }
+    }
+
+    fn alert(&self, trend: Trend) -> Option<Alert> {
+        let now = self.samples.last().unwrap();
+        let minutes = (TREND_WINDOW_MS / 60_000.0) as u32;
+        match trend {
+            Trend::Stable => None,
+            Trend::RainStarting => Some(Alert::warning(
+                now.session_time,
+                format!("rain starting, level {:.0}%", now.rain_level * 100.0),
+            )),
+            Trend::RainRising => Some(Alert::warning(
+                now.session_time,
+                format!(
+                    "rain level rising for {} min — consider wets at next stop",
+                    minutes
+                ),
+            )),
+            Trend::RainEasing => Some(Alert::info(
+                now.session_time,
+                format!("rain easing, level {:.0}%", now.rain_level * 100.0),
+            )),
+            Trend::RainStopped => Some(Alert::info(
+                now.session_time,
+                String::from("rain stopped — watch the track drying"),
+            )),
+            Trend::TrackDrying => Some(Alert::warning(
+                now.session_time,
+                format!(
+                    "track drying for {} min, wetness {:.0}% — consider slicks",
+                    minutes,
+                    now.wetness * 100.0
+                ),
+            )),
+            Trend::TrackWetting => Some(Alert::info(
+                now.session_time,
+                format!("track getting wetter, wetness {:.0}%", now.wetness * 100.0),
+            )),
+        }
+    }
+}